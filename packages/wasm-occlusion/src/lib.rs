@@ -6,20 +6,2498 @@ pub extern "C" fn alloc(size: usize) -> *mut u8 {
     ptr
 }
 
+/// Frees a buffer returned by `alloc`. A null `ptr` is ignored.
+///
+/// # Safety
+///
+/// `ptr` must come from `alloc`, must not be freed twice, and `size` must be
+/// the exact size that was passed to `alloc` for it.
 #[no_mangle]
-pub unsafe extern "C" fn alpha_composite_inplace(ptr_target_rgba: *mut u8, ptr_occluder_rgba: *const u8, len: usize) {
+pub unsafe extern "C" fn dealloc(ptr: *mut u8, size: usize) {
+    if ptr.is_null() {
+        return;
+    }
+
+    drop(Vec::from_raw_parts(ptr, 0, size));
+}
+
+/// Bump whenever an exported function is added, removed or changes signature,
+/// so the host glue can refuse a binary it doesn't match.
+const ABI_VERSION: u32 = 14;
+
+#[no_mangle]
+pub extern "C" fn abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Pointer to the crate version string (UTF-8, not NUL-terminated); read
+/// `crate_version_len()` bytes from it.
+#[no_mangle]
+pub extern "C" fn crate_version() -> *const u8 {
+    env!("CARGO_PKG_VERSION").as_ptr()
+}
+
+#[no_mangle]
+pub extern "C" fn crate_version_len() -> usize {
+    env!("CARGO_PKG_VERSION").len()
+}
+
+/// Returned by the compositing entry points so the host can tell a bad call
+/// apart from one that had nothing to do. Crosses the FFI boundary as an `i32`.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// 0: the call completed, possibly without touching anything.
+    Ok = 0,
+    /// 1: a required pointer was null.
+    NullPointer = 1,
+    /// 2: a byte length was not a whole number of RGBA pixels.
+    UnalignedLen = 2,
+    /// 3: any other rejected argument, such as an unknown mode or a size
+    /// mismatch between buffers.
+    InvalidArgument = 3,
+}
+
+/// Multiplies the target alpha by the inverse of the occluder alpha.
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+    occlude_alpha_slice(target_rgba, occluder_rgba);
+    Status::Ok
+}
+
+/// Applies `count` equally sized occluders to the target in order, in one call.
+/// The occluders live back to back (or anywhere) in one buffer of
+/// `occluders_len` bytes; `ptr_offsets` holds `count` u32 byte offsets into it.
+/// If any occluder would fall outside that buffer, nothing is applied and
+/// `Status::InvalidArgument` is returned.
+///
+/// # Safety
+///
+/// `ptr_target_rgba` must be valid for `len` bytes, `ptr_occluders` for
+/// `occluders_len` bytes and `ptr_offsets` for `count` u32s (no alignment
+/// required). The target must not overlap the occluders.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_batch_inplace(
+    ptr_target_rgba: *mut u8,
+    len: usize,
+    ptr_occluders: *const u8,
+    occluders_len: usize,
+    ptr_offsets: *const u32,
+    count: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluders.is_null() || ptr_offsets.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let offset_at = |i: usize| ptr_offsets.add(i).read_unaligned() as usize;
+    let in_range = |off: usize| off.checked_add(len).is_some_and(|end| end <= occluders_len);
+    if !(0..count).all(|i| in_range(offset_at(i))) {
+        return Status::InvalidArgument;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluders = std::slice::from_raw_parts(ptr_occluders, occluders_len);
+    for i in 0..count {
+        let off = offset_at(i);
+        occlude_alpha_slice(target_rgba, &occluders[off..off + len]);
+    }
+    Status::Ok
+}
+
+fn occlude_alpha(ta: u8, oa: u8) -> u8 {
+    ((ta as u16 * (255 - oa as u16)) / 255) as u8
+}
+
+fn occlude_alpha_slice(target_rgba: &mut [u8], occluder_rgba: &[u8]) {
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    occlude_alpha_simd(target_rgba, occluder_rgba);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    occlude_alpha_scalar(target_rgba, occluder_rgba);
+}
+
+fn occlude_alpha_scalar(target_rgba: &mut [u8], occluder_rgba: &[u8]) {
+    let len = target_rgba.len();
+    let mut i: usize = 3;
+    while i < len {
+        target_rgba[i] = occlude_alpha(target_rgba[i], occluder_rgba[i]);
+        i += 4;
+    }
+}
+
+/// Four pixels per iteration. Every byte goes through the blend, and only the
+/// alpha lanes are kept. `x / 255` is computed as `(y + (y >> 8)) >> 8` with
+/// `y = x + 1`, which is exact for the `0..=65025` products seen here, so the
+/// output matches `occlude_alpha_scalar` byte for byte.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn occlude_alpha_simd(target_rgba: &mut [u8], occluder_rgba: &[u8]) {
+    use core::arch::wasm32::*;
+
+    let alpha_mask = u32x4_splat(0xFF00_0000);
+    let max = u16x8_splat(255);
+    let one = u16x8_splat(1);
+    let div255 = |x: v128| {
+        let y = u16x8_add(x, one);
+        u16x8_shr(u16x8_add(y, u16x8_shr(y, 8)), 8)
+    };
+
+    let mut t_chunks = target_rgba.chunks_exact_mut(16);
+    let mut o_chunks = occluder_rgba.chunks_exact(16);
+    for (t, o) in (&mut t_chunks).zip(&mut o_chunks) {
+        // SAFETY: both chunks are exactly 16 bytes and v128 loads/stores are unaligned.
+        let tv = unsafe { v128_load(t.as_ptr() as *const v128) };
+        let ov = unsafe { v128_load(o.as_ptr() as *const v128) };
+
+        let lo = u16x8_mul(
+            u16x8_extend_low_u8x16(tv),
+            u16x8_sub(max, u16x8_extend_low_u8x16(ov)),
+        );
+        let hi = u16x8_mul(
+            u16x8_extend_high_u8x16(tv),
+            u16x8_sub(max, u16x8_extend_high_u8x16(ov)),
+        );
+        let blended = u8x16_narrow_i16x8(div255(lo), div255(hi));
+
+        let out = v128_bitselect(blended, tv, alpha_mask);
+        unsafe { v128_store(t.as_mut_ptr() as *mut v128, out) };
+    }
+    occlude_alpha_scalar(t_chunks.into_remainder(), o_chunks.remainder());
+}
+
+/// Same as `alpha_composite_inplace`, with the occluder alpha first scaled by
+/// a layer `opacity` (0..=255). An opacity of 0 leaves the target unchanged.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_opacity_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    len: usize,
+    opacity: u8,
+) -> Status {
     if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+    let mut i: usize = 3;
+    while i < len {
+        let oa = (occluder_rgba[i] as u16 * opacity as u16) / 255;
+        target_rgba[i] = occlude_alpha(target_rgba[i], oa as u8);
+        i += 4;
+    }
+    Status::Ok
+}
+
+fn div255(x: u32) -> u32 {
+    (x + 127) / 255
+}
+
+fn source_over_pixel(target: &mut [u8], src: &[u8]) {
+    let sa = src[3] as u32;
+    let ta = target[3] as u32;
+    let src_w = sa * 255;
+    let dst_w = ta * (255 - sa);
+    let out_w = src_w + dst_w;
+    if out_w == 0 {
+        target.fill(0);
         return;
     }
 
+    for c in 0..3 {
+        let num = src[c] as u32 * src_w + target[c] as u32 * dst_w;
+        target[c] = ((num + out_w / 2) / out_w) as u8;
+    }
+    target[3] = div255(out_w) as u8;
+}
+
+/// Porter-Duff "over" on straight (non-premultiplied) RGBA: the source is
+/// drawn on top of the target, blending both color and alpha.
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn source_over_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        source_over_pixel(t, s);
+    }
+    Status::Ok
+}
+
+/// Porter-Duff "over" on premultiplied RGBA. Both buffers must already be
+/// premultiplied (each color channel scaled by its alpha); feeding straight
+/// alpha here produces dark fringes, use `source_over_inplace` for that.
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn source_over_premult_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        let inv_sa = 255 - s[3] as u32;
+        for c in 0..4 {
+            t[c] = (s[c] as u32 + div255(t[c] as u32 * inv_sa)).min(255) as u8;
+        }
+    }
+    Status::Ok
+}
+
+#[derive(Clone, Copy)]
+enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+impl BlendMode {
+    fn from_u32(mode: u32) -> Option<Self> {
+        match mode {
+            0 => Some(Self::Normal),
+            1 => Some(Self::Multiply),
+            2 => Some(Self::Screen),
+            3 => Some(Self::Overlay),
+            _ => None,
+        }
+    }
+
+    fn blend(self, backdrop: u8, src: u8) -> u32 {
+        let b = backdrop as u32;
+        let s = src as u32;
+        match self {
+            Self::Normal => s,
+            Self::Multiply => div255(b * s),
+            Self::Screen => 255 - div255((255 - b) * (255 - s)),
+            Self::Overlay if b < 128 => div255(2 * b * s),
+            Self::Overlay => 255 - div255(2 * (255 - b) * (255 - s)),
+        }
+    }
+}
+
+/// Blends the source into the target with a separable blend mode
+/// (0 = Normal, 1 = Multiply, 2 = Screen, 3 = Overlay), then composites the
+/// result over the target using the source alpha, following the W3C
+/// compositing model. Unknown modes return `Status::InvalidArgument` without
+/// touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn blend_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+    mode: u32,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+    let Some(mode) = BlendMode::from_u32(mode) else {
+        return Status::InvalidArgument;
+    };
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        let ta = t[3] as u32;
+        let mut mixed = [0u8; 4];
+        for c in 0..3 {
+            let blended = mode.blend(t[c], s[c]);
+            mixed[c] = div255((255 - ta) * s[c] as u32 + ta * blended) as u8;
+        }
+        mixed[3] = s[3];
+        source_over_pixel(t, &mixed);
+    }
+    Status::Ok
+}
+
+/// Clips a 1D span of `len` pixels placed at `dst` against `0..bound`,
+/// returning `(src_start, dst_start, count)` or `None` if nothing overlaps.
+fn clip_span(dst: i32, len: u32, bound: u32) -> Option<(usize, usize, usize)> {
+    let start = (dst as i64).max(0);
+    let end = (dst as i64 + len as i64).min(bound as i64);
+    if start >= end {
+        return None;
+    }
+    Some((
+        (start - dst as i64) as usize,
+        start as usize,
+        (end - start) as usize,
+    ))
+}
+
+/// Occludes the `src_w x src_h` region of the target at `(dst_x, dst_y)`
+/// with a tightly packed RGBA source, so small occluders don't need a
+/// full-canvas buffer. Rows of the target are `target_stride` bytes apart,
+/// and the placement is clipped against `target_w x target_h`; a placement
+/// entirely off the target is a successful no-op. The clipped rectangle is
+/// marked on `tracker` unless it is null.
+///
+/// # Safety
+///
+/// `ptr_target_rgba` must be valid for `target_stride * target_h` bytes with
+/// `target_stride >= target_w * 4`, and `ptr_src_rgba` for `src_w * src_h * 4`
+/// bytes. The buffers must not overlap. `tracker` must be null or come from
+/// `dirty_tracker_new`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn alpha_composite_region_inplace(
+    ptr_target_rgba: *mut u8,
+    target_w: u32,
+    target_h: u32,
+    target_stride: u32,
+    dst_x: i32,
+    dst_y: i32,
+    ptr_src_rgba: *const u8,
+    src_w: u32,
+    src_h: u32,
+    tracker: *mut DirtyTracker,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if (target_stride as usize) < target_w as usize * 4 {
+        return Status::InvalidArgument;
+    }
+    let (Some((sx, tx, cols)), Some((sy, ty, rows))) = (
+        clip_span(dst_x, src_w, target_w),
+        clip_span(dst_y, src_h, target_h),
+    ) else {
+        return Status::Ok;
+    };
+    DirtyTracker::mark_if_some(tracker, tx, ty, cols, rows);
+
+    let target_stride = target_stride as usize;
+    let src_stride = src_w as usize * 4;
+    let target_rgba =
+        std::slice::from_raw_parts_mut(ptr_target_rgba, target_stride * target_h as usize);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, src_stride * src_h as usize);
+
+    for row in 0..rows {
+        let t_off = (ty + row) * target_stride + tx * 4;
+        let s_off = (sy + row) * src_stride + sx * 4;
+        let t_row = &mut target_rgba[t_off..t_off + cols * 4];
+        let s_row = &src_rgba[s_off..s_off + cols * 4];
+        for (t, s) in t_row.chunks_exact_mut(4).zip(s_row.chunks_exact(4)) {
+            t[3] = occlude_alpha(t[3], s[3]);
+        }
+    }
+    Status::Ok
+}
+
+/// Inclusive pixel bounds. An empty box has `min > max` on both axes.
+#[derive(Clone, Copy)]
+struct Bbox {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+}
+
+impl Bbox {
+    const EMPTY: Bbox = Bbox {
+        min_x: u32::MAX,
+        min_y: u32::MAX,
+        max_x: 0,
+        max_y: 0,
+    };
+
+    fn include(&mut self, x: u32, y: u32) {
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    fn include_rect(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        self.include(x, y);
+        self.include(x.saturating_add(w - 1), y.saturating_add(h - 1));
+    }
+
+    unsafe fn write_to(self, out: *mut u32) {
+        for (i, v) in [self.min_x, self.min_y, self.max_x, self.max_y]
+            .into_iter()
+            .enumerate()
+        {
+            out.add(i).write_unaligned(v);
+        }
+    }
+}
+
+/// Same blend as `alpha_composite_inplace` over a `width x height` image, and
+/// writes the inclusive bounds of the pixels with nonzero occluder alpha to
+/// `out_bbox_ptr` as four u32s `(min_x, min_y, max_x, max_y)`. When the
+/// occluder is fully transparent it writes `(u32::MAX, u32::MAX, 0, 0)`.
+///
+/// # Safety
+///
+/// Both image pointers must be valid for `width * height * 4` bytes and must
+/// not overlap; `out_bbox_ptr` must be valid for four u32s (no alignment
+/// required).
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_bbox_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    width: u32,
+    height: u32,
+    out_bbox_ptr: *mut u32,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() || out_bbox_ptr.is_null() {
+        return Status::NullPointer;
+    }
+
+    let len = width as usize * height as usize * 4;
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+    let mut bbox = Bbox::EMPTY;
+    let row_len = width as usize * 4;
+    for y in 0..height {
+        let off = y as usize * row_len;
+        let t_row = &mut target_rgba[off..off + row_len];
+        let o_row = &occluder_rgba[off..off + row_len];
+        for (x, (t, o)) in t_row
+            .chunks_exact_mut(4)
+            .zip(o_row.chunks_exact(4))
+            .enumerate()
+        {
+            if o[3] != 0 {
+                t[3] = occlude_alpha(t[3], o[3]);
+                bbox.include(x as u32, y);
+            }
+        }
+    }
+    bbox.write_to(out_bbox_ptr);
+    Status::Ok
+}
+
+/// Hard-edged occlusion: wherever the occluder alpha is `>= threshold` the
+/// target alpha is cleared, elsewhere the target is left as is. A threshold
+/// of 0 clears every pixel; 255 only clears under fully opaque occluder pixels.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_threshold_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    len: usize,
+    threshold: u8,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
     let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
     let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
 
     let mut i: usize = 3;
     while i < len {
-        let ta = target_rgba[i] as u16;
-        let oa = occluder_rgba[i] as u16;
-        target_rgba[i] = ((ta * (255 - oa)) / 255) as u8;
+        if occluder_rgba[i] >= threshold {
+            target_rgba[i] = 0;
+        }
         i += 4;
     }
+    Status::Ok
+}
+
+#[derive(Clone, Copy)]
+enum PixelFormat {
+    Rgba,
+    Bgra,
+}
+
+impl PixelFormat {
+    fn from_u32(fmt: u32) -> Option<Self> {
+        match fmt {
+            0 => Some(Self::Rgba),
+            1 => Some(Self::Bgra),
+            _ => None,
+        }
+    }
+}
+
+/// `source_over_inplace` for either byte order (0 = RGBA, 1 = BGRA); both
+/// buffers must use the same order. Unknown formats return
+/// `Status::InvalidArgument` without touching either buffer.
+///
+/// Alpha is byte 3 in both layouts and the color channels are blended
+/// independently, so both formats share the same per-pixel math.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn source_over_inplace_fmt(
+    ptr_target: *mut u8,
+    ptr_src: *const u8,
+    len: usize,
+    fmt: u32,
+) -> Status {
+    if PixelFormat::from_u32(fmt).is_none() {
+        return Status::InvalidArgument;
+    }
+    source_over_inplace(ptr_target, ptr_src, len)
+}
+
+/// `alpha_composite_inplace` with a one-byte-per-pixel coverage mask as the
+/// occluder. `len` is the target size in bytes and must equal
+/// `pixel_count * 4`, otherwise the call returns `Status::InvalidArgument`
+/// without touching the target.
+///
+/// # Safety
+///
+/// `ptr_target_rgba` must be valid for `len` bytes and `ptr_mask_a8` for
+/// `pixel_count` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_mask_inplace(
+    ptr_target_rgba: *mut u8,
+    len: usize,
+    ptr_mask_a8: *const u8,
+    pixel_count: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_mask_a8.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+    if pixel_count.checked_mul(4) != Some(len) {
+        return Status::InvalidArgument;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let mask_a8 = std::slice::from_raw_parts(ptr_mask_a8, pixel_count);
+
+    for (t, &m) in target_rgba.chunks_exact_mut(4).zip(mask_a8) {
+        t[3] = occlude_alpha(t[3], m);
+    }
+    Status::Ok
+}
+
+/// One box-blur pass over `lines` runs of `len` samples in `buf`. Sample `i`
+/// of line `l` is `buf[l * line_step + i * step]`. The window is clamped to
+/// the line, so edge samples average over fewer neighbours.
+fn box_blur_pass(
+    buf: &mut [u8],
+    len: usize,
+    step: usize,
+    lines: usize,
+    line_step: usize,
+    radius: usize,
+) {
+    let mut prefix = vec![0u32; len + 1];
+    for l in 0..lines {
+        let base = l * line_step;
+        for i in 0..len {
+            prefix[i + 1] = prefix[i] + buf[base + i * step] as u32;
+        }
+        for i in 0..len {
+            let lo = i.saturating_sub(radius);
+            let hi = (i + radius).min(len - 1);
+            let n = (hi - lo + 1) as u32;
+            buf[base + i * step] = ((prefix[hi + 1] - prefix[lo] + n / 2) / n) as u8;
+        }
+    }
+}
+
+/// Separable box blur of the alpha channel only, RGB is left untouched.
+/// Each pass is O(n) regardless of `radius`; a radius larger than the image
+/// simply averages the whole row/column. Radius 0 is a no-op.
+///
+/// Total alpha is conserved (within rounding) only where the window stays
+/// inside the image. Windows clipped at the edges are renormalized over the
+/// samples they still cover, so alpha near a border partly drains away: a
+/// lone opaque corner pixel keeps about 70% of its mass at radius 1.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn box_blur_alpha_inplace(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    radius: u32,
+) {
+    if ptr_rgba.is_null() || radius == 0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, w * h * 4);
+    let alpha = &mut rgba[3..];
+    box_blur_pass(alpha, w, 4, h, w * 4, radius as usize);
+    box_blur_pass(alpha, h, w * 4, w, 4, radius as usize);
+}
+
+fn srgb_to_linear_table() -> &'static [f32; 256] {
+    static TABLE: std::sync::OnceLock<[f32; 256]> = std::sync::OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let c = i as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })
+}
+
+fn linear_to_srgb(l: f32) -> u8 {
+    let c = if l <= 0.003_130_8 {
+        l * 12.92
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// `source_over_inplace` with the color channels blended in linear light:
+/// RGB is decoded from sRGB, composited, then re-encoded. Alpha is treated
+/// as linear coverage in both buffers.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn source_over_linear_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+    let to_linear = srgb_to_linear_table();
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        let sa = s[3] as f32 / 255.0;
+        let ta = t[3] as f32 / 255.0;
+        let dst_w = ta * (1.0 - sa);
+        let out_a = sa + dst_w;
+        if out_a == 0.0 {
+            t.fill(0);
+            continue;
+        }
+
+        for c in 0..3 {
+            let l = (to_linear[s[c] as usize] * sa + to_linear[t[c] as usize] * dst_w) / out_a;
+            t[c] = linear_to_srgb(l);
+        }
+        t[3] = (out_a * 255.0).round() as u8;
+    }
+    Status::Ok
+}
+
+/// Bilinear sample of a `w x h` RGBA image at continuous pixel coordinates
+/// (pixel centers at integers). Coordinates are clamped to the image.
+fn sample_bilinear(src: &[u8], w: usize, h: usize, x: f32, y: f32) -> [u8; 4] {
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let px = |x: usize, y: usize, c: usize| src[(y * w + x) * 4 + c] as f32;
+    std::array::from_fn(|c| {
+        let top = px(x0, y0, c) * (1.0 - fx) + px(x1, y0, c) * fx;
+        let bottom = px(x0, y1, c) * (1.0 - fx) + px(x1, y1, c) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    })
+}
+
+/// Scales a `src_w x src_h` RGBA image into a `dst_w x dst_h` destination
+/// with bilinear filtering, aligning pixel centers. Channels are interpolated
+/// independently (straight alpha). Any zero dimension is a no-op.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes and `ptr_dst` for
+/// `dst_w * dst_h * 4` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn resize_bilinear(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    ptr_dst: *mut u8,
+    dst_w: u32,
+    dst_h: u32,
+) {
+    if ptr_src.is_null()
+        || ptr_dst.is_null()
+        || src_w == 0
+        || src_h == 0
+        || dst_w == 0
+        || dst_h == 0
+    {
+        return;
+    }
+
+    let (sw, sh, dw, dh) = (
+        src_w as usize,
+        src_h as usize,
+        dst_w as usize,
+        dst_h as usize,
+    );
+    let src = std::slice::from_raw_parts(ptr_src, sw * sh * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, dw * dh * 4);
+    let scale_x = sw as f32 / dw as f32;
+    let scale_y = sh as f32 / dh as f32;
+
+    for (i, out) in dst.chunks_exact_mut(4).enumerate() {
+        let sx = ((i % dw) as f32 + 0.5) * scale_x - 0.5;
+        let sy = ((i / dw) as f32 + 0.5) * scale_y - 0.5;
+        out.copy_from_slice(&sample_bilinear(src, sw, sh, sx, sy));
+    }
+}
+
+/// Copies the `w x h` rectangle at `(x, y)` of the source into a tightly
+/// packed destination. Parts of the rectangle outside the source come out
+/// transparent black.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes and `ptr_dst` for
+/// `w * h * 4` bytes, and they must not overlap.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn crop_rect(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    ptr_dst: *mut u8,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() {
+        return;
+    }
+
+    let src = std::slice::from_raw_parts(ptr_src, src_w as usize * src_h as usize * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, w as usize * h as usize * 4);
+    dst.fill(0);
+
+    // Clip the destination rectangle, seen from the source's origin at (-x, -y).
+    let (Some((sx, dx, cols)), Some((sy, dy, rows))) = (
+        clip_span(x.saturating_neg(), src_w, w),
+        clip_span(y.saturating_neg(), src_h, h),
+    ) else {
+        return;
+    };
+
+    let src_stride = src_w as usize * 4;
+    let dst_stride = w as usize * 4;
+    for row in 0..rows {
+        let s_off = (sy + row) * src_stride + sx * 4;
+        let d_off = (dy + row) * dst_stride + dx * 4;
+        dst[d_off..d_off + cols * 4].copy_from_slice(&src[s_off..s_off + cols * 4]);
+    }
+}
+
+/// Mirrors a `width x height` RGBA image left to right in place.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn flip_horizontal_inplace(ptr_rgba: *mut u8, width: u32, height: u32) {
+    if ptr_rgba.is_null() || width <= 1 {
+        return;
+    }
+
+    let row_len = width as usize * 4;
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, row_len * height as usize);
+    for row in rgba.chunks_exact_mut(row_len) {
+        // Reversing the bytes reverses pixel order but also each pixel's
+        // channels; reversing every pixel again restores the channels.
+        row.reverse();
+        for px in row.chunks_exact_mut(4) {
+            px.reverse();
+        }
+    }
+}
+
+/// Mirrors a `width x height` RGBA image top to bottom in place.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn flip_vertical_inplace(ptr_rgba: *mut u8, width: u32, height: u32) {
+    if ptr_rgba.is_null() || height <= 1 {
+        return;
+    }
+
+    let row_len = width as usize * 4;
+    let h = height as usize;
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, row_len * h);
+    for y in 0..h / 2 {
+        let (top, bottom) = rgba.split_at_mut((h - 1 - y) * row_len);
+        top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
+/// Rotates a `src_w x src_h` RGBA image clockwise by `turns` quarter turns
+/// into `ptr_dst`. `turns` wraps modulo 4, and 0 is a plain copy. For odd
+/// turns the destination is `src_h x src_w`.
+///
+/// # Safety
+///
+/// `ptr_src` and `ptr_dst` must both be valid for `src_w * src_h * 4` bytes
+/// and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn rotate_quarter(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    turns: u32,
+    ptr_dst: *mut u8,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() {
+        return;
+    }
+
+    let (w, h) = (src_w as usize, src_h as usize);
+    let src = std::slice::from_raw_parts(ptr_src, w * h * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, w * h * 4);
+
+    for y in 0..h {
+        for x in 0..w {
+            let d = match turns % 4 {
+                0 => y * w + x,
+                1 => x * h + (h - 1 - y),
+                2 => (h - 1 - y) * w + (w - 1 - x),
+                _ => (w - 1 - x) * h + y,
+            };
+            let s = y * w + x;
+            dst[d * 4..d * 4 + 4].copy_from_slice(&src[s * 4..s * 4 + 4]);
+        }
+    }
+}
+
+/// Sets every pixel of the `w x h` rectangle at `(x, y)` to `(r, g, b, a)`,
+/// clipped to the `width x height` buffer. The clipped rectangle is marked on
+/// `tracker` unless it is null.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes. `tracker` must be
+/// null or come from `dirty_tracker_new`.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn fill_rect(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+    tracker: *mut DirtyTracker,
+) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+    let (Some((_, fx, cols)), Some((_, fy, rows))) =
+        (clip_span(x, w, width), clip_span(y, h, height))
+    else {
+        return;
+    };
+    DirtyTracker::mark_if_some(tracker, fx, fy, cols, rows);
+
+    let row_len = width as usize * 4;
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, row_len * height as usize);
+    let color = [r, g, b, a];
+    for row in rgba.chunks_exact_mut(row_len).skip(fy).take(rows) {
+        for px in row[fx * 4..(fx + cols) * 4].chunks_exact_mut(4) {
+            px.copy_from_slice(&color);
+        }
+    }
+}
+
+/// Zeroes `len` bytes, leaving the buffer transparent black.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn clear(ptr_rgba: *mut u8, len: usize) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    std::slice::from_raw_parts_mut(ptr_rgba, len).fill(0);
+}
+
+/// Fills the buffer with the repeating pixel `(r, g, b, a)`. `len` must be a
+/// whole number of RGBA pixels, otherwise the buffer is left untouched.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn clear_color(ptr_rgba: *mut u8, len: usize, r: u8, g: u8, b: u8, a: u8) {
+    if ptr_rgba.is_null() || len == 0 || !len.is_multiple_of(4) {
+        return;
+    }
+
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, len);
+    rgba[..4].copy_from_slice(&[r, g, b, a]);
+    // Double the filled prefix each step so the work is a handful of memcpys.
+    let mut filled = 4;
+    while filled < len {
+        let n = filled.min(len - filled);
+        rgba.copy_within(..n, filled);
+        filled += n;
+    }
+}
+
+/// Copies the alpha byte of each of `pixel_count` RGBA pixels into a tightly
+/// packed one-byte-per-pixel destination.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes and `ptr_dst_a8` for
+/// `pixel_count` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn extract_alpha(
+    ptr_rgba: *const u8,
+    pixel_count: usize,
+    ptr_dst_a8: *mut u8,
+) {
+    if ptr_rgba.is_null() || ptr_dst_a8.is_null() || pixel_count == 0 {
+        return;
+    }
+
+    let rgba = std::slice::from_raw_parts(ptr_rgba, pixel_count * 4);
+    let dst_a8 = std::slice::from_raw_parts_mut(ptr_dst_a8, pixel_count);
+    for (d, px) in dst_a8.iter_mut().zip(rgba.chunks_exact(4)) {
+        *d = px[3];
+    }
 }
+
+fn premultiply_pixel(px: &mut [u8]) {
+    let a = px[3] as u32;
+    for c in &mut px[..3] {
+        *c = div255(*c as u32 * a) as u8;
+    }
+}
+
+fn unpremultiply_pixel(px: &mut [u8]) {
+    let a = px[3] as u32;
+    // Fully transparent pixels carry no color to recover.
+    if a == 0 {
+        return;
+    }
+    for c in &mut px[..3] {
+        *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+    }
+}
+
+/// Converts straight RGBA to premultiplied in place (`c = c * a / 255`).
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn premultiply_inplace(ptr_rgba: *mut u8, len: usize) {
+    if ptr_rgba.is_null() || !len.is_multiple_of(4) {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, len).chunks_exact_mut(4) {
+        premultiply_pixel(px);
+    }
+}
+
+/// Converts premultiplied RGBA back to straight in place
+/// (`c = min(255, c * 255 / a)`). Pixels with zero alpha are left as is.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn unpremultiply_inplace(ptr_rgba: *mut u8, len: usize) {
+    if ptr_rgba.is_null() || !len.is_multiple_of(4) {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, len).chunks_exact_mut(4) {
+        unpremultiply_pixel(px);
+    }
+}
+
+/// Accumulates the union of changed rectangles across several calls, so the
+/// host can upload a single region at the end of a frame.
+///
+/// Only `alpha_composite_region_inplace` and `fill_rect` mark a tracker
+/// themselves, since they clip a caller-placed rectangle and the host can't
+/// easily tell what was actually written. Other rectangle-bounded calls,
+/// such as `alpha_composite_clip_inplace` or the sub-image pointer of
+/// `alpha_composite_strided_inplace`, touch exactly the rectangle the host
+/// passed in; mark it with `dirty_tracker_mark`.
+pub struct DirtyTracker {
+    bbox: Bbox,
+}
+
+impl DirtyTracker {
+    unsafe fn mark_if_some(tracker: *mut DirtyTracker, x: usize, y: usize, w: usize, h: usize) {
+        if let Some(tracker) = tracker.as_mut() {
+            tracker
+                .bbox
+                .include_rect(x as u32, y as u32, w as u32, h as u32);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn dirty_tracker_new() -> *mut DirtyTracker {
+    Box::into_raw(Box::new(DirtyTracker { bbox: Bbox::EMPTY }))
+}
+
+/// Unions the `w x h` rectangle at `(x, y)` into the tracked region. Empty
+/// rectangles are ignored.
+///
+/// # Safety
+///
+/// `tracker` must be null or come from `dirty_tracker_new` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn dirty_tracker_mark(
+    tracker: *mut DirtyTracker,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+) {
+    if let Some(tracker) = tracker.as_mut() {
+        tracker.bbox.include_rect(x, y, w, h);
+    }
+}
+
+/// Writes the tracked region to `out_ptr` as four u32s
+/// `(min_x, min_y, max_x, max_y)`, inclusive. Nothing marked yet reads as
+/// `(u32::MAX, u32::MAX, 0, 0)`.
+///
+/// # Safety
+///
+/// `tracker` must be null or come from `dirty_tracker_new` and not be freed;
+/// `out_ptr` must be valid for four u32s (no alignment required).
+#[no_mangle]
+pub unsafe extern "C" fn dirty_tracker_get(tracker: *const DirtyTracker, out_ptr: *mut u32) {
+    if out_ptr.is_null() {
+        return;
+    }
+    if let Some(tracker) = tracker.as_ref() {
+        tracker.bbox.write_to(out_ptr);
+    }
+}
+
+/// # Safety
+///
+/// `tracker` must be null or come from `dirty_tracker_new`, and must not be
+/// used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn dirty_tracker_free(tracker: *mut DirtyTracker) {
+    if !tracker.is_null() {
+        drop(Box::from_raw(tracker));
+    }
+}
+
+/// Flattens `layer_count` stacked RGBA layers of `len` bytes each, stored
+/// back to back bottom first, into `ptr_dst` with `source_over_inplace`
+/// semantics. The bottom layer is copied as is, so a single layer is a plain
+/// copy. A `layer_count` of 0 returns `Status::InvalidArgument`.
+///
+/// # Safety
+///
+/// `ptr_layers` must be valid for `layer_count * len` bytes and `ptr_dst` for
+/// `len` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn flatten_layers(
+    ptr_layers: *const u8,
+    layer_count: usize,
+    len: usize,
+    ptr_dst: *mut u8,
+) -> Status {
+    if ptr_layers.is_null() || ptr_dst.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+    let Some(total) = layer_count.checked_mul(len).filter(|_| layer_count != 0) else {
+        return Status::InvalidArgument;
+    };
+
+    let layers = std::slice::from_raw_parts(ptr_layers, total);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, len);
+    let (bottom, rest) = layers.split_at(len);
+    dst.copy_from_slice(bottom);
+
+    for layer in rest.chunks_exact(len) {
+        for (t, s) in dst.chunks_exact_mut(4).zip(layer.chunks_exact(4)) {
+            source_over_pixel(t, s);
+        }
+    }
+    Status::Ok
+}
+
+const ARENA_ALIGN: usize = 16;
+const ARENA_MIN_BLOCK: usize = 64 * 1024;
+
+/// Bump allocator for per-frame scratch buffers. Running out of space adds a
+/// new block rather than growing one in place, so pointers handed out earlier
+/// in the frame stay valid. `reset` replaces several blocks with one fresh
+/// block of their combined size, so the frame after a growth sees new
+/// addresses; from then on, frames that fit get the same addresses back.
+struct Arena {
+    blocks: Vec<Vec<u8>>,
+    used: usize,
+}
+
+impl Arena {
+    const fn new() -> Self {
+        Arena {
+            blocks: Vec::new(),
+            used: 0,
+        }
+    }
+
+    fn alloc(&mut self, size: usize) -> *mut u8 {
+        if let Some(block) = self.blocks.last_mut() {
+            let base = block.as_mut_ptr() as usize;
+            let start = (base + self.used).next_multiple_of(ARENA_ALIGN) - base;
+            if let Some(end) = start
+                .checked_add(size)
+                .filter(|&end| end <= block.capacity())
+            {
+                self.used = end;
+                return block.as_mut_ptr().wrapping_add(start);
+            }
+        }
+
+        let last_cap = self.blocks.last().map_or(0, Vec::capacity);
+        let (Some(padded), Some(doubled)) =
+            (size.checked_add(ARENA_ALIGN), last_cap.checked_mul(2))
+        else {
+            return std::ptr::null_mut();
+        };
+        let mut block = Vec::new();
+        if block
+            .try_reserve_exact(padded.max(ARENA_MIN_BLOCK).max(doubled))
+            .is_err()
+        {
+            return std::ptr::null_mut();
+        }
+        self.blocks.push(block);
+        self.used = 0;
+        self.alloc(size)
+    }
+
+    fn reset(&mut self) {
+        if self.blocks.len() > 1 {
+            let total = self.blocks.iter().map(Vec::capacity).sum();
+            self.blocks.clear();
+            self.blocks.push(Vec::with_capacity(total));
+        }
+        self.used = 0;
+    }
+}
+
+static ARENA: std::sync::Mutex<Arena> = std::sync::Mutex::new(Arena::new());
+
+/// Hands out `size` bytes (16-byte aligned) from the frame arena. The memory
+/// stays valid until the next `arena_reset` and must not be passed to
+/// `dealloc`. Returns null if the arena cannot grow by `size` bytes.
+#[no_mangle]
+pub extern "C" fn arena_alloc(size: usize) -> *mut u8 {
+    ARENA.lock().unwrap_or_else(|e| e.into_inner()).alloc(size)
+}
+
+/// Releases everything handed out by `arena_alloc` at once, for reuse by the
+/// next frame. Earlier arena pointers must not be used afterwards.
+#[no_mangle]
+pub extern "C" fn arena_reset() {
+    ARENA.lock().unwrap_or_else(|e| e.into_inner()).reset();
+}
+
+/// `alpha_composite_inplace` for 16-bit-per-channel RGBA. Each pixel is
+/// 8 bytes: four little-endian u16 channels with alpha last, so the alpha of
+/// pixel `i` is bytes `i * 8 + 6..i * 8 + 8`. `len` is the byte size of each
+/// buffer and must equal `pixel_count * 8`, otherwise nothing is touched and
+/// `Status::InvalidArgument` is returned.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap. No
+/// alignment is required.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_inplace_u16(
+    ptr_target_u16: *mut u8,
+    ptr_occluder_u16: *const u8,
+    len: usize,
+    pixel_count: usize,
+) -> Status {
+    if ptr_target_u16.is_null() || ptr_occluder_u16.is_null() {
+        return Status::NullPointer;
+    }
+    if pixel_count.checked_mul(8) != Some(len) {
+        return Status::InvalidArgument;
+    }
+
+    let target = std::slice::from_raw_parts_mut(ptr_target_u16, len);
+    let occluder = std::slice::from_raw_parts(ptr_occluder_u16, len);
+
+    for (t, o) in target.chunks_exact_mut(8).zip(occluder.chunks_exact(8)) {
+        let ta = u16::from_le_bytes([t[6], t[7]]) as u32;
+        let oa = u16::from_le_bytes([o[6], o[7]]) as u32;
+        let out = ((ta * (65535 - oa)) / 65535) as u16;
+        t[6..8].copy_from_slice(&out.to_le_bytes());
+    }
+    Status::Ok
+}
+
+/// Writes the count of each alpha value over `pixel_count` RGBA pixels to the
+/// 256 u32s at `ptr_hist`. The histogram is overwritten, not accumulated, so
+/// zero pixels yield all zeros.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes and `ptr_hist` for
+/// 256 u32s (no alignment required).
+#[no_mangle]
+pub unsafe extern "C" fn alpha_histogram(
+    ptr_rgba: *const u8,
+    pixel_count: usize,
+    ptr_hist: *mut u32,
+) {
+    if ptr_hist.is_null() || (ptr_rgba.is_null() && pixel_count != 0) {
+        return;
+    }
+
+    let mut hist = [0u32; 256];
+    if pixel_count != 0 {
+        for px in std::slice::from_raw_parts(ptr_rgba, pixel_count * 4).chunks_exact(4) {
+            hist[px[3] as usize] += 1;
+        }
+    }
+    for (i, count) in hist.into_iter().enumerate() {
+        ptr_hist.add(i).write_unaligned(count);
+    }
+}
+
+/// Writes the smallest and largest alpha over `pixel_count` RGBA pixels to
+/// `out_min_max_ptr` as two bytes `(min, max)`, so the host can skip fully
+/// transparent occluders (`max == 0`) or clear outright under fully opaque
+/// ones (`min == 255`). Scanning stops once both 0 and 255 have been seen.
+/// Zero pixels write `(255, 0)`.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes and `out_min_max_ptr`
+/// for two bytes.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_min_max(
+    ptr_rgba: *const u8,
+    pixel_count: usize,
+    out_min_max_ptr: *mut u8,
+) {
+    if out_min_max_ptr.is_null() || (ptr_rgba.is_null() && pixel_count != 0) {
+        return;
+    }
+
+    let (mut min, mut max) = (255u8, 0u8);
+    if pixel_count != 0 {
+        for px in std::slice::from_raw_parts(ptr_rgba, pixel_count * 4).chunks_exact(4) {
+            min = min.min(px[3]);
+            max = max.max(px[3]);
+            if min == 0 && max == 255 {
+                break;
+            }
+        }
+    }
+    *out_min_max_ptr = min;
+    *out_min_max_ptr.add(1) = max;
+}
+
+/// Finds the largest axis-aligned rectangle whose pixels all have alpha 255,
+/// so layers entirely behind it can be culled, and writes its inclusive bounds
+/// to `out_bbox_ptr` as four u32s `(min_x, min_y, max_x, max_y)`. With no
+/// fully opaque pixel it writes `(u32::MAX, u32::MAX, 0, 0)`.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes and `out_bbox_ptr`
+/// for four u32s (no alignment required).
+#[no_mangle]
+pub unsafe extern "C" fn opaque_bbox(
+    ptr_rgba: *const u8,
+    width: u32,
+    height: u32,
+    out_bbox_ptr: *mut u32,
+) {
+    if ptr_rgba.is_null() || out_bbox_ptr.is_null() {
+        return;
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let rgba = std::slice::from_raw_parts(ptr_rgba, w * h * 4);
+
+    // Per row, `heights[x]` is the run of opaque pixels ending at this row in
+    // column x; the best rectangle ending here is the largest one under that
+    // histogram, found with a stack of increasing heights.
+    let mut heights = vec![0usize; w];
+    let mut stack: Vec<usize> = Vec::with_capacity(w + 1);
+    let mut best_area = 0;
+    let mut best = Bbox::EMPTY;
+    for y in 0..h {
+        for (x, run) in heights.iter_mut().enumerate() {
+            *run = if rgba[(y * w + x) * 4 + 3] == 255 {
+                *run + 1
+            } else {
+                0
+            };
+        }
+
+        stack.clear();
+        for x in 0..=w {
+            let cur = if x < w { heights[x] } else { 0 };
+            while let Some(&top) = stack.last() {
+                if heights[top] < cur {
+                    break;
+                }
+                stack.pop();
+                let left = stack.last().map_or(0, |&l| l + 1);
+                let area = heights[top] * (x - left);
+                if area > best_area {
+                    best_area = area;
+                    best = Bbox {
+                        min_x: left as u32,
+                        min_y: (y + 1 - heights[top]) as u32,
+                        max_x: (x - 1) as u32,
+                        max_y: y as u32,
+                    };
+                }
+            }
+            stack.push(x);
+        }
+    }
+    best.write_to(out_bbox_ptr);
+}
+
+/// One pass of a min/max filter over the same line layout as
+/// `box_blur_pass`. `pick` is `u8::min` for erosion or `u8::max` for dilation;
+/// the window is clamped to the line.
+fn morph_pass(
+    buf: &mut [u8],
+    len: usize,
+    step: usize,
+    lines: usize,
+    line_step: usize,
+    radius: usize,
+    pick: fn(u8, u8) -> u8,
+) {
+    let mut line = vec![0u8; len];
+    for l in 0..lines {
+        let base = l * line_step;
+        for (i, v) in line.iter_mut().enumerate() {
+            *v = buf[base + i * step];
+        }
+        for i in 0..len {
+            let lo = i.saturating_sub(radius);
+            let hi = (i + radius).min(len - 1);
+            buf[base + i * step] = line[lo..=hi].iter().copied().reduce(pick).unwrap_or(0);
+        }
+    }
+}
+
+unsafe fn alpha_morph_inplace(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    radius: u32,
+    pick: fn(u8, u8) -> u8,
+) {
+    if ptr_rgba.is_null() || radius == 0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, w * h * 4);
+    let alpha = &mut rgba[3..];
+    morph_pass(alpha, w, 4, h, w * 4, radius as usize, pick);
+    morph_pass(alpha, h, w * 4, w, 4, radius as usize, pick);
+}
+
+/// Replaces each alpha with the minimum over the `(2 * radius + 1)` square
+/// around it, clamped at the borders, shrinking shapes by `radius` pixels.
+/// RGB is untouched and radius 0 is a no-op.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_erode_inplace(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    radius: u32,
+) {
+    alpha_morph_inplace(ptr_rgba, width, height, radius, u8::min);
+}
+
+/// Replaces each alpha with the maximum over the `(2 * radius + 1)` square
+/// around it, clamped at the borders, growing shapes by `radius` pixels.
+/// RGB is untouched and radius 0 is a no-op.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_dilate_inplace(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    radius: u32,
+) {
+    alpha_morph_inplace(ptr_rgba, width, height, radius, u8::max);
+}
+
+/// `alpha_composite_inplace` over a `width x height` image, restricted to the
+/// `clip_w x clip_h` rectangle at `(clip_x, clip_y)`. Pixels outside the
+/// clip, which is itself clipped to the image, are left untouched.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `width * height * 4` bytes and must not
+/// overlap.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn alpha_composite_clip_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    width: u32,
+    height: u32,
+    clip_x: i32,
+    clip_y: i32,
+    clip_w: u32,
+    clip_h: u32,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    let (Some((_, x, cols)), Some((_, y, rows))) = (
+        clip_span(clip_x, clip_w, width),
+        clip_span(clip_y, clip_h, height),
+    ) else {
+        return Status::Ok;
+    };
+
+    let row_len = width as usize * 4;
+    let len = row_len * height as usize;
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+    for row in y..y + rows {
+        let start = row * row_len + x * 4;
+        let end = start + cols * 4;
+        occlude_alpha_slice(&mut target_rgba[start..end], &occluder_rgba[start..end]);
+    }
+    Status::Ok
+}
+
+/// Turns a color-keyed image into a regular occluder: pixels whose RGB is
+/// within `tolerance` of `(key_r, key_g, key_b)`, measured as squared
+/// Euclidean distance, get alpha 0. Other pixels are left as is, and a
+/// tolerance of 0 keys exact matches only.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chroma_key_to_alpha_inplace(
+    ptr_rgba: *mut u8,
+    pixel_count: usize,
+    key_r: u8,
+    key_g: u8,
+    key_b: u8,
+    tolerance: u32,
+) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    let key = [key_r, key_g, key_b];
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4).chunks_exact_mut(4) {
+        let dist2: u32 = px[..3]
+            .iter()
+            .zip(key)
+            .map(|(&c, k)| (c.abs_diff(k) as u32).pow(2))
+            .sum();
+        if dist2 <= tolerance {
+            px[3] = 0;
+        }
+    }
+}
+
+/// Sets every pixel's RGB to `(r, g, b)`, keeping its alpha, so a shape
+/// becomes a flat color with the same coverage. For straight-alpha buffers.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tint_inplace(ptr_rgba: *mut u8, pixel_count: usize, r: u8, g: u8, b: u8) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4).chunks_exact_mut(4) {
+        px[..3].copy_from_slice(&[r, g, b]);
+    }
+}
+
+/// `tint_inplace` for premultiplied buffers: the tint color is scaled by each
+/// pixel's alpha.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tint_premult_inplace(
+    ptr_rgba: *mut u8,
+    pixel_count: usize,
+    r: u8,
+    g: u8,
+    b: u8,
+) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4).chunks_exact_mut(4) {
+        px[..3].copy_from_slice(&[r, g, b]);
+        premultiply_pixel(px);
+    }
+}
+
+/// Rec.709 luma in 16.16 fixed point. The weights sum to exactly 65536 so
+/// white maps to 255 and the result never overflows a byte.
+fn luma709(r: u8, g: u8, b: u8) -> u8 {
+    ((13933 * r as u32 + 46871 * g as u32 + 4732 * b as u32 + 32768) >> 16) as u8
+}
+
+/// Replaces each pixel's RGB with its Rec.709 luma, keeping alpha. Uses
+/// integer weights so every host produces the same bytes.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn grayscale_inplace(ptr_rgba: *mut u8, pixel_count: usize) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4).chunks_exact_mut(4) {
+        let y = luma709(px[0], px[1], px[2]);
+        px[..3].fill(y);
+    }
+}
+
+/// Calls `average` with each 2x2 block of a `src_w x src_h` RGBA image and
+/// writes the result to the matching pixel of the `src_w / 2 x src_h / 2`
+/// destination. A trailing odd row or column is dropped.
+fn downsample_2x_with(
+    src: &[u8],
+    src_w: usize,
+    dst: &mut [u8],
+    average: impl Fn([&[u8]; 4], &mut [u8]),
+) {
+    let dst_w = src_w / 2;
+    if dst_w == 0 {
+        return;
+    }
+    for (dy, dst_row) in dst.chunks_exact_mut(dst_w * 4).enumerate() {
+        let top = 2 * dy * src_w * 4;
+        let bottom = top + src_w * 4;
+        for (dx, out) in dst_row.chunks_exact_mut(4).enumerate() {
+            let (l, r) = (dx * 8, dx * 8 + 4);
+            let block = [
+                &src[top + l..top + l + 4],
+                &src[top + r..top + r + 4],
+                &src[bottom + l..bottom + l + 4],
+                &src[bottom + r..bottom + r + 4],
+            ];
+            average(block, out);
+        }
+    }
+}
+
+/// Halves a `src_w x src_h` RGBA image by averaging each 2x2 block per
+/// channel (straight alpha, fine for previews) into a
+/// `src_w / 2 x src_h / 2` destination. With an odd width or height the last
+/// column or row is dropped.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes and `ptr_dst` for
+/// `(src_w / 2) * (src_h / 2) * 4` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn downsample_2x(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    ptr_dst: *mut u8,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() {
+        return;
+    }
+
+    let (sw, sh) = (src_w as usize, src_h as usize);
+    let src = std::slice::from_raw_parts(ptr_src, sw * sh * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, (sw / 2) * (sh / 2) * 4);
+    downsample_2x_with(src, sw, dst, |block, out| {
+        for (c, o) in out.iter_mut().enumerate() {
+            let sum: u32 = block.iter().map(|px| px[c] as u32).sum();
+            *o = ((sum + 2) / 4) as u8;
+        }
+    });
+}
+
+/// `downsample_2x` with premultiplied averaging: each color is weighted by its
+/// alpha before the 2x2 average and divided back out afterwards, so
+/// transparent pixels contribute no color and a fully transparent block
+/// comes out transparent black. The sums are kept at full precision rather
+/// than rounding through premultiplied bytes.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes and `ptr_dst` for
+/// `(src_w / 2) * (src_h / 2) * 4` bytes, and they must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn downsample_2x_premult(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    ptr_dst: *mut u8,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() {
+        return;
+    }
+
+    let (sw, sh) = (src_w as usize, src_h as usize);
+    let src = std::slice::from_raw_parts(ptr_src, sw * sh * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, (sw / 2) * (sh / 2) * 4);
+    downsample_2x_with(src, sw, dst, |block, out| {
+        let alpha_sum: u32 = block.iter().map(|px| px[3] as u32).sum();
+        out[3] = ((alpha_sum + 2) / 4) as u8;
+        for c in 0..3 {
+            let weighted: u32 = block.iter().map(|px| px[c] as u32 * px[3] as u32).sum();
+            out[c] = (weighted + alpha_sum / 2)
+                .checked_div(alpha_sum)
+                .unwrap_or(0) as u8;
+        }
+    });
+}
+
+fn apply_rgb_lut(rgba: &mut [u8], lut: &[u8; 256]) {
+    for px in rgba.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = lut[*c as usize];
+        }
+    }
+}
+
+/// Raises each RGB channel to the power `gamma_x1000 / 1000` through a
+/// 256-entry lookup table, keeping alpha. Exponents above 1 darken midtones,
+/// below 1 lighten them. `1000` is the identity and `0` is ignored; both
+/// leave the buffer untouched.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn adjust_gamma_inplace(
+    ptr_rgba: *mut u8,
+    pixel_count: usize,
+    gamma_x1000: u32,
+) {
+    if ptr_rgba.is_null() || gamma_x1000 == 1000 || gamma_x1000 == 0 {
+        return;
+    }
+
+    let gamma = gamma_x1000 as f32 / 1000.0;
+    let lut: [u8; 256] =
+        std::array::from_fn(|i| ((i as f32 / 255.0).powf(gamma) * 255.0).round() as u8);
+    apply_rgb_lut(
+        std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4),
+        &lut,
+    );
+}
+
+/// Applies contrast `contrast_x1000 / 1000` about mid-gray (128), then adds
+/// `brightness`, to each RGB channel through one 256-entry lookup table,
+/// clamping to 0..=255 and keeping alpha. `brightness == 0` with
+/// `contrast_x1000 == 1000` is the identity and leaves the buffer untouched.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn adjust_brightness_contrast_inplace(
+    ptr_rgba: *mut u8,
+    pixel_count: usize,
+    brightness: i32,
+    contrast_x1000: u32,
+) {
+    if ptr_rgba.is_null() || (brightness == 0 && contrast_x1000 == 1000) {
+        return;
+    }
+
+    let contrast = contrast_x1000 as f32 / 1000.0;
+    let brightness = brightness as f32;
+    let lut: [u8; 256] = std::array::from_fn(|i| {
+        ((i as f32 - 128.0) * contrast + 128.0 + brightness)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    });
+    apply_rgb_lut(
+        std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4),
+        &lut,
+    );
+}
+
+/// One convolution pass with a symmetric `kernel` (odd length, centered) over
+/// the same line layout as `box_blur_pass`. Near the ends of a line the
+/// kernel is cut off and the remaining weights renormalized.
+fn convolve_pass(
+    buf: &mut [u8],
+    len: usize,
+    step: usize,
+    lines: usize,
+    line_step: usize,
+    kernel: &[f32],
+) {
+    let radius = kernel.len() / 2;
+    let mut line = vec![0f32; len];
+    for l in 0..lines {
+        let base = l * line_step;
+        for (i, v) in line.iter_mut().enumerate() {
+            *v = buf[base + i * step] as f32;
+        }
+        for i in 0..len {
+            let lo = i.saturating_sub(radius);
+            let hi = (i + radius).min(len - 1);
+            let (mut sum, mut weight) = (0.0, 0.0);
+            for (j, v) in line[lo..=hi].iter().enumerate() {
+                let k = kernel[lo + j + radius - i];
+                sum += v * k;
+                weight += k;
+            }
+            buf[base + i * step] = (sum / weight).round() as u8;
+        }
+    }
+}
+
+/// Separable Gaussian blur of the alpha channel only, RGB is left untouched.
+/// `sigma_x1000` is the standard deviation in thousandths of a pixel and the
+/// kernel extends to `ceil(3 * sigma)` on each side, capped at the longer
+/// image side since taps past a line are never read. Sigma 0 is a no-op.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gaussian_blur_alpha_inplace(
+    ptr_rgba: *mut u8,
+    width: u32,
+    height: u32,
+    sigma_x1000: u32,
+) {
+    if ptr_rgba.is_null() || sigma_x1000 == 0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let sigma = sigma_x1000 as f32 / 1000.0;
+    let radius = ((3.0 * sigma).ceil() as i64).min(width.max(height) as i64 - 1);
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|x| (-(x as f32).powi(2) / (2.0 * sigma * sigma)).exp())
+        .collect();
+
+    let (w, h) = (width as usize, height as usize);
+    let rgba = std::slice::from_raw_parts_mut(ptr_rgba, w * h * 4);
+    let alpha = &mut rgba[3..];
+    convolve_pass(alpha, w, 4, h, w * 4, &kernel);
+    convolve_pass(alpha, h, w * 4, w, 4, &kernel);
+}
+
+/// `alpha_composite_inplace` split across the rayon thread pool when built
+/// with the `parallel` feature; otherwise it runs the regular single-threaded
+/// path. Chunks are whole pixels, so the output is identical either way.
+///
+/// Native builds only, for host-side tooling. The module has no wasm-bindgen
+/// glue to spawn a thread pool from the browser, so wasm32 builds leave this
+/// export out instead of shipping one that would never run in parallel.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[cfg(not(target_arch = "wasm32"))]
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_inplace_parallel(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    len: usize,
+) -> Status {
+    #[cfg(not(feature = "parallel"))]
+    return alpha_composite_inplace(ptr_target_rgba, ptr_occluder_rgba, len);
+
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+
+        if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+            return Status::NullPointer;
+        }
+        if !len.is_multiple_of(4) {
+            return Status::UnalignedLen;
+        }
+
+        let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+        let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+        // One chunk per thread, rounded up to a whole pixel.
+        let pixels = len / 4;
+        let chunk = pixels.div_ceil(rayon::current_num_threads()).max(1) * 4;
+        target_rgba
+            .par_chunks_mut(chunk)
+            .zip(occluder_rgba.par_chunks(chunk))
+            .for_each(|(t, o)| occlude_alpha_slice(t, o));
+        Status::Ok
+    }
+}
+
+/// `source_over_inplace` with the source alpha of each pixel first scaled by
+/// a one-byte-per-pixel coverage mask (`src_a * mask / 255`), e.g. to clip a
+/// fill to a text shape. `len` is the byte size of the target and the source
+/// and must equal `pixel_count * 4`, otherwise the call returns
+/// `Status::InvalidArgument` without touching the target.
+///
+/// # Safety
+///
+/// `ptr_target_rgba` and `ptr_src_rgba` must be valid for `len` bytes and
+/// `ptr_mask_a8` for `pixel_count` bytes. The target must not overlap the
+/// other two.
+#[no_mangle]
+pub unsafe extern "C" fn source_over_masked_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+    ptr_mask_a8: *const u8,
+    pixel_count: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() || ptr_mask_a8.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+    if pixel_count.checked_mul(4) != Some(len) {
+        return Status::InvalidArgument;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+    let mask_a8 = std::slice::from_raw_parts(ptr_mask_a8, pixel_count);
+
+    for ((t, s), &m) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+        .zip(mask_a8)
+    {
+        let masked = [s[0], s[1], s[2], div255(s[3] as u32 * m as u32) as u8];
+        source_over_pixel(t, &masked);
+    }
+    Status::Ok
+}
+
+/// Porter-Duff "destination-out": `out_a = target_a * (1 - src_a)`, RGB
+/// unchanged. The same operation as `alpha_composite_inplace` under its
+/// Porter-Duff name.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn destination_out_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    alpha_composite_inplace(ptr_target_rgba, ptr_src_rgba, len)
+}
+
+/// Porter-Duff "destination-in": `out_a = target_a * src_a`, RGB unchanged,
+/// keeping the target only where the source covers it.
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn destination_in_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        t[3] = ((t[3] as u16 * s[3] as u16) / 255) as u8;
+    }
+    Status::Ok
+}
+
+/// `alpha_composite_inplace` over a `width x height` region whose rows are
+/// `target_stride` and `occluder_stride` bytes apart, e.g. a sub-image of an
+/// atlas. Bytes between rows are never touched. Both strides must be at least
+/// `width * 4`, otherwise `Status::InvalidArgument` is returned.
+///
+/// # Safety
+///
+/// Each pointer must be valid for `(height - 1) * stride + width * 4` bytes
+/// with its own stride, and the two regions must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_strided_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    width: u32,
+    height: u32,
+    target_stride: u32,
+    occluder_stride: u32,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    let row_len = width as usize * 4;
+    let (t_stride, o_stride) = (target_stride as usize, occluder_stride as usize);
+    if t_stride < row_len || o_stride < row_len {
+        return Status::InvalidArgument;
+    }
+    if width == 0 || height == 0 {
+        return Status::Ok;
+    }
+
+    let rows = height as usize;
+    let target_rgba =
+        std::slice::from_raw_parts_mut(ptr_target_rgba, (rows - 1) * t_stride + row_len);
+    let occluder_rgba =
+        std::slice::from_raw_parts(ptr_occluder_rgba, (rows - 1) * o_stride + row_len);
+
+    for y in 0..rows {
+        let (t, o) = (y * t_stride, y * o_stride);
+        occlude_alpha_slice(
+            &mut target_rgba[t..t + row_len],
+            &occluder_rgba[o..o + row_len],
+        );
+    }
+    Status::Ok
+}
+
+/// Writes a one-byte-per-pixel coverage mask of a `width x height` rectangle
+/// with quarter-circle corners of `radius` (clamped to `min(width, height) / 2`).
+/// Pixels are full coverage except near the arcs, where coverage falls off
+/// linearly over one pixel with the distance of the pixel center from the arc.
+///
+/// # Safety
+///
+/// `ptr_dst_a8` must be valid for `width * height` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn generate_rounded_rect_mask(
+    ptr_dst_a8: *mut u8,
+    width: u32,
+    height: u32,
+    radius: u32,
+) {
+    if ptr_dst_a8.is_null() {
+        return;
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst_a8, w * h);
+    let r = radius.min(width.min(height) / 2) as f32;
+    let (fw, fh) = (width as f32, height as f32);
+
+    for (i, out) in dst.iter_mut().enumerate() {
+        let px = (i % w) as f32 + 0.5;
+        let py = (i / w) as f32 + 0.5;
+        // Offset from the nearest point of the inner rectangle the corner arcs
+        // are centered on. Only pixels off both of its axes lie in a corner;
+        // the straight edges are pixel aligned and always fully covered.
+        let dx = px - px.clamp(r, fw - r);
+        let dy = py - py.clamp(r, fh - r);
+        if dx == 0.0 || dy == 0.0 {
+            *out = 255;
+            continue;
+        }
+        let dist = (dx * dx + dy * dy).sqrt();
+        let coverage = (r - dist + 0.5).clamp(0.0, 1.0);
+        *out = (coverage * 255.0).round() as u8;
+    }
+}
+
+/// Softens a `width x height` A8 mask by ramping alpha linearly to zero over
+/// the `edge_px` pixels nearest a fully transparent pixel. Distance is the
+/// chessboard approximation from a two-pass chamfer scan; pixels beyond the
+/// image border are not treated as transparent. Alpha is only ever scaled
+/// down, and `edge_px == 0` is a no-op.
+///
+/// # Safety
+///
+/// `ptr_a8` must be valid for `width * height` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn feather_mask_inplace(
+    ptr_a8: *mut u8,
+    width: u32,
+    height: u32,
+    edge_px: u32,
+) {
+    if ptr_a8.is_null() || edge_px == 0 || width == 0 || height == 0 {
+        return;
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let mask = std::slice::from_raw_parts_mut(ptr_a8, w * h);
+    // Distances saturate just past the ramp; anything farther is left alone.
+    let far = edge_px.saturating_add(1);
+    let mut dist: Vec<u32> = mask.iter().map(|&a| if a == 0 { 0 } else { far }).collect();
+
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let mut d = dist[i];
+            if y > 0 {
+                let up = i - w;
+                d = d.min(dist[up].saturating_add(1));
+                if x > 0 {
+                    d = d.min(dist[up - 1].saturating_add(1));
+                }
+                if x + 1 < w {
+                    d = d.min(dist[up + 1].saturating_add(1));
+                }
+            }
+            if x > 0 {
+                d = d.min(dist[i - 1].saturating_add(1));
+            }
+            dist[i] = d;
+        }
+    }
+    for y in (0..h).rev() {
+        for x in (0..w).rev() {
+            let i = y * w + x;
+            let mut d = dist[i];
+            if y + 1 < h {
+                let down = i + w;
+                d = d.min(dist[down].saturating_add(1));
+                if x > 0 {
+                    d = d.min(dist[down - 1].saturating_add(1));
+                }
+                if x + 1 < w {
+                    d = d.min(dist[down + 1].saturating_add(1));
+                }
+            }
+            if x + 1 < w {
+                d = d.min(dist[i + 1].saturating_add(1));
+            }
+            dist[i] = d;
+        }
+    }
+
+    for (a, &d) in mask.iter_mut().zip(&dist) {
+        if d == 0 || d > edge_px {
+            continue;
+        }
+        // Ramp sampled at pixel centers: the boundary pixel sits half a
+        // pixel in, so the ramp spans exactly `edge_px` pixels.
+        let ramp = ((2 * d as u64 - 1) * 255 / (2 * edge_px as u64)) as u32;
+        *a = div255(*a as u32 * ramp) as u8;
+    }
+}
+
+/// Bilinear sample like `sample_bilinear`, except taps outside the image read
+/// as transparent black instead of clamping, so edges fade out. Samples more
+/// than a pixel outside (or non-finite ones) come back fully transparent.
+fn sample_bilinear_border(src: &[u8], w: usize, h: usize, x: f32, y: f32) -> [u8; 4] {
+    if !(x > -1.0 && x < w as f32 && y > -1.0 && y < h as f32) {
+        return [0; 4];
+    }
+    let (xf, yf) = (x.floor(), y.floor());
+    let (x0, y0) = (xf as isize, yf as isize);
+    let (fx, fy) = (x - xf, y - yf);
+
+    let px = |x: isize, y: isize, c: usize| {
+        if x < 0 || y < 0 || x as usize >= w || y as usize >= h {
+            0.0
+        } else {
+            src[(y as usize * w + x as usize) * 4 + c] as f32
+        }
+    };
+    std::array::from_fn(|c| {
+        let top = px(x0, y0, c) * (1.0 - fx) + px(x0 + 1, y0, c) * fx;
+        let bottom = px(x0, y0 + 1, c) * (1.0 - fx) + px(x0 + 1, y0 + 1, c) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    })
+}
+
+/// Resamples a `src_w x src_h` RGBA image into a `dst_w x dst_h` destination
+/// through an affine transform. `matrix_ptr` holds six f32s `(a, b, c, d, e,
+/// f)` of the inverse transform in canvas order, mapping a destination point
+/// to `(a * x + c * y + e, b * x + d * y + f)` in the source. Both spaces put
+/// pixel `i` on `[i, i + 1)`, so pixel centers sit at `i + 0.5`. Sampling is
+/// bilinear on straight alpha, and anything outside the source is
+/// transparent. Any zero dimension is a no-op.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes, `ptr_dst` for
+/// `dst_w * dst_h * 4` bytes, and `matrix_ptr` for six f32s. The images must
+/// not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn warp_affine(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    ptr_dst: *mut u8,
+    dst_w: u32,
+    dst_h: u32,
+    matrix_ptr: *const f32,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() || matrix_ptr.is_null() {
+        return;
+    }
+    if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+        return;
+    }
+
+    let (sw, sh, dw, dh) = (
+        src_w as usize,
+        src_h as usize,
+        dst_w as usize,
+        dst_h as usize,
+    );
+    let src = std::slice::from_raw_parts(ptr_src, sw * sh * 4);
+    let dst = std::slice::from_raw_parts_mut(ptr_dst, dw * dh * 4);
+    let [a, b, c, d, e, f] = std::ptr::read_unaligned(matrix_ptr as *const [f32; 6]);
+
+    for (i, out) in dst.chunks_exact_mut(4).enumerate() {
+        let x = (i % dw) as f32 + 0.5;
+        let y = (i / dw) as f32 + 0.5;
+        let sx = a * x + c * y + e - 0.5;
+        let sy = b * x + d * y + f - 0.5;
+        out.copy_from_slice(&sample_bilinear_border(src, sw, sh, sx, sy));
+    }
+}
+
+/// Writes each pixel's Rec.709 luma into its alpha channel, or `255 - luma`
+/// when `invert != 0`, so a white-on-black painted mask becomes a cutout.
+/// RGB is untouched.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `pixel_count * 4` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn luminance_to_alpha_inplace(
+    ptr_rgba: *mut u8,
+    pixel_count: usize,
+    invert: u32,
+) {
+    if ptr_rgba.is_null() {
+        return;
+    }
+
+    for px in std::slice::from_raw_parts_mut(ptr_rgba, pixel_count * 4).chunks_exact_mut(4) {
+        let y = luma709(px[0], px[1], px[2]);
+        px[3] = if invert != 0 { 255 - y } else { y };
+    }
+}
+
+/// Additive ("linear dodge") blend on straight RGBA for glow and light
+/// effects: each target color channel gains the source channel weighted by
+/// source alpha, saturating at 255. The result keeps the larger of the two
+/// alphas, so a glow can widen the target's coverage but never reduce it,
+/// and a fully transparent source is a no-op.
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching either buffer.
+///
+/// # Safety
+///
+/// Both pointers must be valid for `len` bytes and must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn add_blend_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_src_rgba: *const u8,
+    len: usize,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_src_rgba.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let src_rgba = std::slice::from_raw_parts(ptr_src_rgba, len);
+
+    for (t, s) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(src_rgba.chunks_exact(4))
+    {
+        let sa = s[3] as u32;
+        for c in 0..3 {
+            t[c] = (t[c] as u32 + div255(s[c] as u32 * sa)).min(255) as u8;
+        }
+        t[3] = t[3].max(s[3]);
+    }
+    Status::Ok
+}
+
+/// Same blend as `alpha_composite_inplace`, counting how the occluder touched
+/// each pixel. Writes three u32s to `out_stats_ptr`: pixels with occluder
+/// alpha 255 (fully occluded), 1..=254 (partial) and 0 (untouched).
+///
+/// `len` must be a whole number of RGBA pixels; if `len % 4 != 0` the call
+/// returns `Status::UnalignedLen` without touching any buffer.
+///
+/// # Safety
+///
+/// Both image pointers must be valid for `len` bytes and must not overlap;
+/// `out_stats_ptr` must be valid for three u32s (no alignment required).
+#[no_mangle]
+pub unsafe extern "C" fn alpha_composite_stats_inplace(
+    ptr_target_rgba: *mut u8,
+    ptr_occluder_rgba: *const u8,
+    len: usize,
+    out_stats_ptr: *mut u32,
+) -> Status {
+    if ptr_target_rgba.is_null() || ptr_occluder_rgba.is_null() || out_stats_ptr.is_null() {
+        return Status::NullPointer;
+    }
+    if !len.is_multiple_of(4) {
+        return Status::UnalignedLen;
+    }
+
+    let target_rgba = std::slice::from_raw_parts_mut(ptr_target_rgba, len);
+    let occluder_rgba = std::slice::from_raw_parts(ptr_occluder_rgba, len);
+
+    let mut stats = [0u32; 3];
+    for (t, o) in target_rgba
+        .chunks_exact_mut(4)
+        .zip(occluder_rgba.chunks_exact(4))
+    {
+        t[3] = occlude_alpha(t[3], o[3]);
+        let bucket = match o[3] {
+            255 => 0,
+            0 => 2,
+            _ => 1,
+        };
+        stats[bucket] += 1;
+    }
+    (out_stats_ptr as *mut [u32; 3]).write_unaligned(stats);
+    Status::Ok
+}
+
+fn nonzero_alpha_bbox(rgba: &[u8], width: usize) -> Bbox {
+    let mut bbox = Bbox::EMPTY;
+    for (i, px) in rgba.chunks_exact(4).enumerate() {
+        if px[3] != 0 {
+            bbox.include((i % width) as u32, (i / width) as u32);
+        }
+    }
+    bbox
+}
+
+/// Writes the inclusive bounds of the pixels with nonzero alpha in a `width x
+/// height` RGBA image to `out_bbox_ptr` as four u32s `(min_x, min_y, max_x,
+/// max_y)`, or `(u32::MAX, u32::MAX, 0, 0)` when the image is fully
+/// transparent. Use it to size the destination of `trim_transparent`.
+///
+/// # Safety
+///
+/// `ptr_rgba` must be valid for `width * height * 4` bytes and `out_bbox_ptr`
+/// for four u32s (no alignment required).
+#[no_mangle]
+pub unsafe extern "C" fn alpha_bbox(
+    ptr_rgba: *const u8,
+    width: u32,
+    height: u32,
+    out_bbox_ptr: *mut u32,
+) {
+    if ptr_rgba.is_null() || out_bbox_ptr.is_null() {
+        return;
+    }
+
+    let rgba = std::slice::from_raw_parts(ptr_rgba, width as usize * height as usize * 4);
+    nonzero_alpha_bbox(rgba, width as usize).write_to(out_bbox_ptr);
+}
+
+/// Trims the transparent margins off a `src_w x src_h` RGBA image: writes the
+/// `alpha_bbox` bounds to `out_bbox_ptr` and copies that rectangle, tightly
+/// packed, into `ptr_dst`. A fully transparent image writes the empty box and
+/// copies nothing.
+///
+/// # Safety
+///
+/// `ptr_src` must be valid for `src_w * src_h * 4` bytes and `out_bbox_ptr`
+/// for four u32s (no alignment required). `ptr_dst` must be valid for the
+/// trimmed size, `(max_x - min_x + 1) * (max_y - min_y + 1) * 4` bytes as
+/// reported by `alpha_bbox`, and must not overlap the source.
+#[no_mangle]
+pub unsafe extern "C" fn trim_transparent(
+    ptr_src: *const u8,
+    src_w: u32,
+    src_h: u32,
+    ptr_dst: *mut u8,
+    out_bbox_ptr: *mut u32,
+) {
+    if ptr_src.is_null() || ptr_dst.is_null() || out_bbox_ptr.is_null() {
+        return;
+    }
+
+    let src = std::slice::from_raw_parts(ptr_src, src_w as usize * src_h as usize * 4);
+    let bbox = nonzero_alpha_bbox(src, src_w as usize);
+    bbox.write_to(out_bbox_ptr);
+    if bbox.min_x > bbox.max_x {
+        return;
+    }
+    crop_rect(
+        ptr_src,
+        src_w,
+        src_h,
+        bbox.min_x as i32,
+        bbox.min_y as i32,
+        bbox.max_x - bbox.min_x + 1,
+        bbox.max_y - bbox.min_y + 1,
+        ptr_dst,
+    );
+}
+
+#[cfg(test)]
+mod tests;
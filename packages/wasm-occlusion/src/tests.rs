@@ -0,0 +1,1911 @@
+use super::*;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts live heap bytes per thread, so tests running in parallel don't see
+/// each other's allocations.
+struct CountingAlloc;
+
+thread_local! {
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = LIVE_BYTES.try_with(|n| n.set(n.get() + layout.size() as isize));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE_BYTES.try_with(|n| n.set(n.get() - layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn live_bytes() -> isize {
+    LIVE_BYTES.with(Cell::get)
+}
+
+#[test]
+fn alloc_dealloc_round_trips_leave_no_live_bytes() {
+    let baseline = live_bytes();
+    for size in (0..2000).step_by(7) {
+        let ptr = alloc(size);
+        assert!(!ptr.is_null());
+        assert_eq!(live_bytes(), baseline + size as isize);
+        unsafe { dealloc(ptr, size) };
+        assert_eq!(live_bytes(), baseline);
+    }
+}
+
+#[test]
+fn dealloc_null_is_a_no_op() {
+    let baseline = live_bytes();
+    unsafe { dealloc(std::ptr::null_mut(), 64) };
+    assert_eq!(live_bytes(), baseline);
+}
+
+#[test]
+fn alpha_composite_rejects_partial_pixel_len_untouched() {
+    let mut target = [200u8; 10];
+    let occluder = [255u8; 10];
+    let status = unsafe { alpha_composite_inplace(target.as_mut_ptr(), occluder.as_ptr(), 10) };
+    assert_eq!(status, Status::UnalignedLen);
+    assert_eq!(target, [200u8; 10]);
+    assert_eq!(occluder, [255u8; 10]);
+}
+
+/// Straight-alpha "over" in f64, the reference for `source_over_inplace`.
+fn reference_over(target: [u8; 4], src: [u8; 4]) -> [u8; 4] {
+    let sa = src[3] as f64 / 255.0;
+    let ta = target[3] as f64 / 255.0;
+    let out_a = sa + ta * (1.0 - sa);
+    if out_a == 0.0 {
+        return [0; 4];
+    }
+    let mut out = [0u8; 4];
+    for c in 0..3 {
+        out[c] = ((src[c] as f64 * sa + target[c] as f64 * ta * (1.0 - sa)) / out_a).round() as u8;
+    }
+    out[3] = (out_a * 255.0).round() as u8;
+    out
+}
+
+#[test]
+fn source_over_matches_float_reference() {
+    let cases = [
+        ([0, 255, 0, 255], [255, 0, 0, 128]),
+        ([10, 20, 30, 40], [200, 100, 50, 60]),
+        ([255, 255, 255, 0], [0, 0, 0, 1]),
+        ([90, 180, 45, 255], [12, 34, 56, 255]),
+        ([120, 40, 200, 77], [0, 0, 0, 0]),
+        ([5, 6, 7, 0], [8, 9, 10, 0]),
+    ];
+    for (target, src) in cases {
+        let mut out = target;
+        assert_eq!(
+            unsafe { source_over_inplace(out.as_mut_ptr(), src.as_ptr(), 4) },
+            Status::Ok
+        );
+        let expected = reference_over(target, src);
+        for c in 0..4 {
+            assert!(
+                out[c].abs_diff(expected[c]) <= 1,
+                "{target:?} over {src:?}: {out:?} vs {expected:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn source_over_transparent_on_transparent_is_transparent_black() {
+    let mut target = [5u8, 6, 7, 0];
+    unsafe { source_over_inplace(target.as_mut_ptr(), [8, 9, 10, 0].as_ptr(), 4) };
+    assert_eq!(target, [0; 4]);
+}
+
+#[test]
+fn source_over_premult_half_red_over_opaque_green() {
+    // 50% red premultiplied is (128, 0, 0, 128).
+    let mut target = [0u8, 255, 0, 255];
+    let status =
+        unsafe { source_over_premult_inplace(target.as_mut_ptr(), [128, 0, 0, 128].as_ptr(), 4) };
+    assert_eq!(status, Status::Ok);
+    assert_eq!(target, [128, 127, 0, 255]);
+}
+
+/// Deterministic filler for tests that want arbitrary pixel data.
+fn pseudo_random_bytes(seed: u32, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn opacity_zero_leaves_target_unchanged() {
+    let target = pseudo_random_bytes(1, 256);
+    let occluder = pseudo_random_bytes(2, 256);
+    let mut out = target.clone();
+    unsafe { alpha_composite_opacity_inplace(out.as_mut_ptr(), occluder.as_ptr(), out.len(), 0) };
+    assert_eq!(out, target);
+}
+
+#[test]
+fn opacity_half_halves_an_opaque_occluder() {
+    let mut target = [9u8, 9, 9, 255, 9, 9, 9, 200];
+    let occluder = [0u8, 0, 0, 255, 0, 0, 0, 255];
+    unsafe { alpha_composite_opacity_inplace(target.as_mut_ptr(), occluder.as_ptr(), 8, 128) };
+    assert_eq!(target, [9, 9, 9, 127, 9, 9, 9, 99]);
+}
+
+#[test]
+fn opacity_full_matches_alpha_composite() {
+    let target = pseudo_random_bytes(3, 4096);
+    let occluder = pseudo_random_bytes(4, 4096);
+    let mut plain = target.clone();
+    let mut full = target.clone();
+    unsafe {
+        alpha_composite_inplace(plain.as_mut_ptr(), occluder.as_ptr(), plain.len());
+        alpha_composite_opacity_inplace(full.as_mut_ptr(), occluder.as_ptr(), full.len(), 255);
+    }
+    assert_eq!(plain, full);
+}
+
+fn blend_pixel(target: [u8; 4], src: [u8; 4], mode: u32) -> (Status, [u8; 4]) {
+    let mut out = target;
+    let status = unsafe { blend_inplace(out.as_mut_ptr(), src.as_ptr(), 4, mode) };
+    (status, out)
+}
+
+#[test]
+fn blend_modes_match_hand_computed_values() {
+    let backdrop = [200, 100, 50, 255];
+    let src = [100, 200, 255, 255];
+    // Normal takes the source.
+    assert_eq!(blend_pixel(backdrop, src, 0), (Status::Ok, src));
+    // Multiply: b * s / 255.
+    assert_eq!(
+        blend_pixel(backdrop, src, 1),
+        (Status::Ok, [78, 78, 50, 255])
+    );
+    // Screen: 255 - (255 - b) * (255 - s) / 255.
+    assert_eq!(
+        blend_pixel(backdrop, src, 2),
+        (Status::Ok, [222, 222, 255, 255])
+    );
+    // Overlay: multiply below mid-gray backdrop, screen above.
+    assert_eq!(
+        blend_pixel(backdrop, src, 3),
+        (Status::Ok, [188, 157, 100, 255])
+    );
+}
+
+#[test]
+fn blend_composites_with_source_alpha() {
+    let (_, out) = blend_pixel([200, 200, 200, 255], [100, 100, 100, 128], 1);
+    assert_eq!(out, [139, 139, 139, 255]);
+}
+
+#[test]
+fn blend_unknown_mode_is_rejected_untouched() {
+    let backdrop = [200, 100, 50, 255];
+    assert_eq!(
+        blend_pixel(backdrop, [1, 2, 3, 255], 4),
+        (Status::InvalidArgument, backdrop)
+    );
+}
+
+/// 4x3 opaque target with rows padded to 20 bytes; the padding is 0xAA so
+/// stray writes past a row show up.
+fn padded_region_target() -> Vec<u8> {
+    let mut target = vec![0xAAu8; 20 * 3];
+    for row in target.chunks_exact_mut(20) {
+        row[..16].copy_from_slice(&[0, 0, 0, 255].repeat(4));
+    }
+    target
+}
+
+fn region_alphas(target: &[u8]) -> Vec<u8> {
+    target
+        .chunks_exact(20)
+        .flat_map(|row| row[..16].chunks_exact(4).map(|px| px[3]))
+        .collect()
+}
+
+fn composite_region(dst_x: i32, dst_y: i32) -> Vec<u8> {
+    let src = [255u8; 2 * 2 * 4];
+    let mut target = padded_region_target();
+    let status = unsafe {
+        alpha_composite_region_inplace(
+            target.as_mut_ptr(),
+            4,
+            3,
+            20,
+            dst_x,
+            dst_y,
+            src.as_ptr(),
+            2,
+            2,
+            std::ptr::null_mut(),
+        )
+    };
+    assert_eq!(status, Status::Ok);
+    assert!(target.chunks_exact(20).all(|row| row[16..] == [0xAA; 4]));
+    target
+}
+
+#[test]
+fn region_fully_inside() {
+    let target = composite_region(1, 1);
+    assert_eq!(
+        region_alphas(&target),
+        [255, 255, 255, 255, 255, 0, 0, 255, 255, 0, 0, 255]
+    );
+}
+
+#[test]
+fn region_clipped_on_the_left() {
+    let target = composite_region(-1, 0);
+    assert_eq!(
+        region_alphas(&target),
+        [0, 255, 255, 255, 0, 255, 255, 255, 255, 255, 255, 255]
+    );
+}
+
+#[test]
+fn region_fully_off_canvas_is_a_no_op() {
+    assert_eq!(composite_region(10, -5), padded_region_target());
+    assert_eq!(composite_region(-2, 0), padded_region_target());
+}
+
+#[test]
+fn simd_div255_identity_is_exact_for_occlusion_products() {
+    // `occlude_alpha_simd` divides with `(y + (y >> 8)) >> 8`, `y = x + 1`.
+    for x in 0..=255u32 * 255 {
+        let y = x + 1;
+        assert_eq!((y + (y >> 8)) >> 8, x / 255, "{x}");
+    }
+}
+
+/// Only built for `wasm32` with `simd128`, which host `cargo test` never is:
+/// `RUSTFLAGS="-C target-feature=+simd128" cargo test --target wasm32-wasip1`
+/// runs it through the wasmtime runner in `.cargo/config.toml`.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[test]
+fn simd_occlusion_matches_scalar() {
+    // Lengths around the 16-byte SIMD step, so the scalar tail is covered.
+    for len in [0, 4, 12, 16, 20, 28, 32, 60, 1024, 4100] {
+        let target = pseudo_random_bytes(len as u32, len);
+        let occluder = pseudo_random_bytes(len as u32 + 1, len);
+        let mut simd = target.clone();
+        let mut scalar = target.clone();
+        occlude_alpha_simd(&mut simd, &occluder);
+        occlude_alpha_scalar(&mut scalar, &occluder);
+        assert_eq!(simd, scalar, "len {len}");
+    }
+}
+
+#[test]
+fn batch_matches_sequential_single_calls() {
+    let len = 64;
+    let target = pseudo_random_bytes(9, len);
+    let occluders = pseudo_random_bytes(10, len * 3 + 8);
+    let offsets = [0u32, 72, 64];
+
+    let mut sequential = target.clone();
+    for &off in &offsets {
+        unsafe {
+            alpha_composite_inplace(
+                sequential.as_mut_ptr(),
+                occluders[off as usize..].as_ptr(),
+                len,
+            )
+        };
+    }
+    let mut batched = target.clone();
+    let status = unsafe {
+        alpha_composite_batch_inplace(
+            batched.as_mut_ptr(),
+            len,
+            occluders.as_ptr(),
+            occluders.len(),
+            offsets.as_ptr(),
+            3,
+        )
+    };
+    assert_eq!(status, Status::Ok);
+    assert_eq!(batched, sequential);
+}
+
+#[test]
+fn batch_out_of_range_offset_is_rejected_untouched() {
+    let len = 64;
+    let target = pseudo_random_bytes(11, len);
+    let occluders = pseudo_random_bytes(12, len * 2);
+    // The first offset is fine; the second runs past the end.
+    let offsets = [0u32, len as u32 + 4];
+    let mut out = target.clone();
+    let status = unsafe {
+        alpha_composite_batch_inplace(
+            out.as_mut_ptr(),
+            len,
+            occluders.as_ptr(),
+            occluders.len(),
+            offsets.as_ptr(),
+            2,
+        )
+    };
+    assert_eq!(status, Status::InvalidArgument);
+    assert_eq!(out, target);
+}
+
+fn composite_bbox(occluder: &[u8], width: u32, height: u32) -> (Vec<u8>, [u32; 4]) {
+    let mut target = vec![255u8; occluder.len()];
+    let mut bbox = [7u32; 4];
+    let status = unsafe {
+        alpha_composite_bbox_inplace(
+            target.as_mut_ptr(),
+            occluder.as_ptr(),
+            width,
+            height,
+            bbox.as_mut_ptr(),
+        )
+    };
+    assert_eq!(status, Status::Ok);
+    (target, bbox)
+}
+
+#[test]
+fn bbox_of_a_single_pixel() {
+    let mut occluder = vec![0u8; 4 * 3 * 4];
+    occluder[(2 * 4 + 1) * 4 + 3] = 255;
+    let (target, bbox) = composite_bbox(&occluder, 4, 3);
+    assert_eq!(bbox, [1, 2, 1, 2]);
+    assert_eq!(target[(2 * 4 + 1) * 4 + 3], 0);
+}
+
+#[test]
+fn bbox_of_the_full_buffer() {
+    let (_, bbox) = composite_bbox(&[10u8; 4 * 3 * 4], 4, 3);
+    assert_eq!(bbox, [0, 0, 3, 2]);
+}
+
+#[test]
+fn bbox_of_a_transparent_occluder_is_empty() {
+    let (target, bbox) = composite_bbox(&[0u8; 4 * 3 * 4], 4, 3);
+    assert_eq!(bbox, [u32::MAX, u32::MAX, 0, 0]);
+    assert!(target.iter().all(|&b| b == 255));
+}
+
+fn alphas(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|px| px[3]).collect()
+}
+
+fn composite_threshold(threshold: u8) -> Vec<u8> {
+    let occluder = [0, 0, 0, 0, 0, 0, 0, 128, 0, 0, 0, 254, 0, 0, 0, 255u8];
+    let mut target = [9u8; 16];
+    unsafe {
+        alpha_composite_threshold_inplace(target.as_mut_ptr(), occluder.as_ptr(), 16, threshold)
+    };
+    alphas(&target)
+}
+
+#[test]
+fn threshold_zero_clears_everything() {
+    assert_eq!(composite_threshold(0), [0, 0, 0, 0]);
+}
+
+#[test]
+fn threshold_255_only_clears_opaque_occluders() {
+    assert_eq!(composite_threshold(255), [9, 9, 9, 0]);
+}
+
+#[test]
+fn threshold_is_inclusive() {
+    assert_eq!(composite_threshold(128), [9, 0, 0, 0]);
+}
+
+fn over_fmt(target: [u8; 4], src: [u8; 4], fmt: u32) -> (Status, [u8; 4]) {
+    let mut out = target;
+    let status = unsafe { source_over_inplace_fmt(out.as_mut_ptr(), src.as_ptr(), 4, fmt) };
+    (status, out)
+}
+
+#[test]
+fn over_fmt_keeps_red_in_its_byte_for_each_order() {
+    // Half-transparent red over opaque blue; red is byte 0 in RGBA, byte 2 in BGRA.
+    assert_eq!(
+        over_fmt([0, 0, 255, 255], [255, 0, 0, 128], 0),
+        (Status::Ok, [128, 0, 127, 255])
+    );
+    assert_eq!(
+        over_fmt([255, 0, 0, 255], [0, 0, 255, 128], 1),
+        (Status::Ok, [127, 0, 128, 255])
+    );
+}
+
+#[test]
+fn over_fmt_unknown_format_is_rejected_untouched() {
+    assert_eq!(
+        over_fmt([1, 2, 3, 4], [0, 0, 255, 255], 2),
+        (Status::InvalidArgument, [1, 2, 3, 4])
+    );
+}
+
+#[test]
+fn mask_matches_expanded_rgba_occluder() {
+    let pixels = 300;
+    let target = pseudo_random_bytes(13, pixels * 4);
+    let mask = pseudo_random_bytes(14, pixels);
+    let expanded: Vec<u8> = mask.iter().flat_map(|&a| [0, 0, 0, a]).collect();
+
+    let mut via_rgba = target.clone();
+    let mut via_mask = target.clone();
+    unsafe {
+        alpha_composite_inplace(via_rgba.as_mut_ptr(), expanded.as_ptr(), pixels * 4);
+        assert_eq!(
+            alpha_composite_mask_inplace(via_mask.as_mut_ptr(), pixels * 4, mask.as_ptr(), pixels),
+            Status::Ok
+        );
+    }
+    assert_eq!(via_mask, via_rgba);
+}
+
+#[test]
+fn mask_len_mismatch_is_rejected_untouched() {
+    let target = pseudo_random_bytes(15, 40);
+    let mask = [255u8; 10];
+    let mut out = target.clone();
+    let status = unsafe { alpha_composite_mask_inplace(out.as_mut_ptr(), 36, mask.as_ptr(), 10) };
+    assert_eq!(status, Status::InvalidArgument);
+    assert_eq!(out, target);
+}
+
+/// `width x height` of RGB 7 with a single opaque pixel at `(x, y)`.
+fn lone_opaque_pixel(width: usize, height: usize, x: usize, y: usize) -> Vec<u8> {
+    let mut rgba = [7, 7, 7, 0].repeat(width * height);
+    rgba[(y * width + x) * 4 + 3] = 255;
+    rgba
+}
+
+#[test]
+fn box_blur_radius_zero_is_a_no_op() {
+    let mut rgba = lone_opaque_pixel(9, 9, 4, 4);
+    let before = rgba.clone();
+    unsafe { box_blur_alpha_inplace(rgba.as_mut_ptr(), 9, 9, 0) };
+    assert_eq!(rgba, before);
+}
+
+#[test]
+fn box_blur_spreads_over_the_radius_footprint() {
+    let mut rgba = lone_opaque_pixel(9, 9, 4, 4);
+    unsafe { box_blur_alpha_inplace(rgba.as_mut_ptr(), 9, 9, 1) };
+    let alpha = alphas(&rgba);
+    for y in 0..9 {
+        for x in 0..9 {
+            let inside = (3..=5).contains(&x) && (3..=5).contains(&y);
+            assert_eq!(alpha[y * 9 + x], if inside { 28 } else { 0 }, "({x}, {y})");
+        }
+    }
+    assert!(rgba.chunks_exact(4).all(|px| px[..3] == [7, 7, 7]));
+}
+
+#[test]
+fn box_blur_conserves_interior_mass() {
+    for radius in 1..=3 {
+        let mut rgba = lone_opaque_pixel(11, 11, 5, 5);
+        unsafe { box_blur_alpha_inplace(rgba.as_mut_ptr(), 11, 11, radius) };
+        let mass: u32 = alphas(&rgba).iter().map(|&a| a as u32).sum();
+        // Each output rounds to the nearest byte, so allow half a level per
+        // pixel of the footprint.
+        let footprint = (2 * radius + 1).pow(2);
+        assert!(
+            mass.abs_diff(255) <= footprint / 2 + 1,
+            "radius {radius}: {mass}"
+        );
+    }
+}
+
+#[test]
+fn linear_over_differs_from_gamma_over() {
+    let src = [128u8, 128, 128, 128];
+    let mut gamma = [255u8, 0, 0, 255];
+    let mut linear = gamma;
+    unsafe {
+        source_over_inplace(gamma.as_mut_ptr(), src.as_ptr(), 4);
+        assert_eq!(
+            source_over_linear_inplace(linear.as_mut_ptr(), src.as_ptr(), 4),
+            Status::Ok
+        );
+    }
+    assert_eq!(gamma, [191, 64, 64, 255]);
+    // Mixing in linear light keeps the blend brighter than the sRGB average.
+    assert!(
+        linear[0] > gamma[0] && linear[1] > gamma[1] && linear[2] > gamma[2],
+        "{linear:?}"
+    );
+    assert_eq!(linear[3], 255);
+}
+
+#[test]
+fn linear_over_keeps_same_color_and_opaque_source() {
+    for v in [0u8, 1, 50, 128, 200, 255] {
+        let mut target = [v, v, v, 255];
+        unsafe { source_over_linear_inplace(target.as_mut_ptr(), [v, v, v, 200].as_ptr(), 4) };
+        assert_eq!(target, [v, v, v, 255]);
+    }
+    let mut target = [1u8, 2, 3, 4];
+    unsafe { source_over_linear_inplace(target.as_mut_ptr(), [9, 9, 9, 255].as_ptr(), 4) };
+    assert_eq!(target, [9, 9, 9, 255]);
+}
+
+fn gray(v: u8) -> [u8; 4] {
+    [v, v, v, 255]
+}
+
+fn reds(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|px| px[0]).collect()
+}
+
+#[test]
+fn resize_upscales_a_checker_2x() {
+    let src = [gray(0), gray(255), gray(255), gray(0)].concat();
+    let mut dst = vec![0u8; 4 * 4 * 4];
+    unsafe { resize_bilinear(src.as_ptr(), 2, 2, dst.as_mut_ptr(), 4, 4) };
+    #[rustfmt::skip]
+    let expected = [
+        0, 64, 191, 255,
+        64, 96, 159, 191,
+        191, 159, 96, 64,
+        255, 191, 64, 0,
+    ];
+    assert_eq!(reds(&dst), expected);
+    assert!(alphas(&dst).iter().all(|&a| a == 255));
+}
+
+#[test]
+fn resize_downscales_4x4_to_2x2() {
+    let src: Vec<u8> = (0..16)
+        .flat_map(|i| gray((i % 4 * 10 + i / 4 * 40) as u8))
+        .collect();
+    let mut dst = vec![0u8; 2 * 2 * 4];
+    unsafe { resize_bilinear(src.as_ptr(), 4, 4, dst.as_mut_ptr(), 2, 2) };
+    assert_eq!(reds(&dst), [25, 45, 105, 125]);
+}
+
+#[test]
+fn resize_zero_dimension_is_a_no_op() {
+    let src = [gray(1), gray(2), gray(3), gray(4)].concat();
+    let mut dst = vec![9u8; 16];
+    unsafe {
+        resize_bilinear(src.as_ptr(), 0, 2, dst.as_mut_ptr(), 2, 2);
+        resize_bilinear(src.as_ptr(), 2, 2, dst.as_mut_ptr(), 2, 0);
+    }
+    assert_eq!(dst, [9u8; 16]);
+}
+
+/// `width x height` image whose red channel is the pixel index.
+fn numbered(width: usize, height: usize) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| [i as u8, 1, 2, 255])
+        .collect()
+}
+
+fn crop(src: &[u8], x: i32, y: i32, w: u32, h: u32) -> Vec<u8> {
+    let mut dst = vec![9u8; (w * h * 4) as usize];
+    unsafe { crop_rect(src.as_ptr(), 4, 4, x, y, w, h, dst.as_mut_ptr()) };
+    dst
+}
+
+#[test]
+fn crop_interior() {
+    let src = numbered(4, 4);
+    assert_eq!(reds(&crop(&src, 1, 1, 2, 2)), [5, 6, 9, 10]);
+}
+
+#[test]
+fn crop_overhanging_right_and_bottom_pads_transparent() {
+    let src = numbered(4, 4);
+    let expected = [
+        [11, 1, 2, 255],
+        [0; 4],
+        [15, 1, 2, 255],
+        [0; 4],
+        [0; 4],
+        [0; 4],
+    ]
+    .concat();
+    assert_eq!(crop(&src, 3, 2, 2, 3), expected);
+}
+
+#[test]
+fn crop_fully_outside_is_transparent() {
+    let src = numbered(4, 4);
+    assert!(crop(&src, 10, 0, 2, 2).iter().all(|&b| b == 0));
+    assert!(crop(&src, -5, -5, 2, 2).iter().all(|&b| b == 0));
+}
+
+#[test]
+fn flip_horizontal_reverses_each_row() {
+    let mut rgba = numbered(3, 2);
+    unsafe { flip_horizontal_inplace(rgba.as_mut_ptr(), 3, 2) };
+    assert_eq!(reds(&rgba), [2, 1, 0, 5, 4, 3]);
+    assert!(rgba.chunks_exact(4).all(|px| px[1..] == [1, 2, 255]));
+}
+
+#[test]
+fn flip_vertical_reverses_row_order() {
+    let mut rgba = numbered(2, 3);
+    unsafe { flip_vertical_inplace(rgba.as_mut_ptr(), 2, 3) };
+    assert_eq!(reds(&rgba), [4, 5, 2, 3, 0, 1]);
+    let mut rgba = numbered(2, 4);
+    unsafe { flip_vertical_inplace(rgba.as_mut_ptr(), 2, 4) };
+    assert_eq!(reds(&rgba), [6, 7, 4, 5, 2, 3, 0, 1]);
+}
+
+#[test]
+fn flip_along_a_single_pixel_axis_is_a_no_op() {
+    let mut column = numbered(1, 3);
+    unsafe { flip_horizontal_inplace(column.as_mut_ptr(), 1, 3) };
+    assert_eq!(column, numbered(1, 3));
+    let mut row = numbered(3, 1);
+    unsafe { flip_vertical_inplace(row.as_mut_ptr(), 3, 1) };
+    assert_eq!(row, numbered(3, 1));
+}
+
+/// Rotates the 3x2 image `0 1 2 / 3 4 5` and returns the red channel.
+fn rotate_3x2(turns: u32) -> Vec<u8> {
+    let src = numbered(3, 2);
+    let mut dst = vec![0u8; src.len()];
+    unsafe { rotate_quarter(src.as_ptr(), 3, 2, turns, dst.as_mut_ptr()) };
+    reds(&dst)
+}
+
+#[test]
+fn rotate_quarter_maps_corners() {
+    // Clockwise: the bottom-left corner (3) becomes the top-left.
+    assert_eq!(rotate_3x2(1), [3, 0, 4, 1, 5, 2]);
+    assert_eq!(rotate_3x2(2), [5, 4, 3, 2, 1, 0]);
+    assert_eq!(rotate_3x2(3), [2, 5, 1, 4, 0, 3]);
+}
+
+#[test]
+fn rotate_quarter_wraps_turns() {
+    assert_eq!(rotate_3x2(0), [0, 1, 2, 3, 4, 5]);
+    assert_eq!(rotate_3x2(4), rotate_3x2(0));
+    assert_eq!(rotate_3x2(5), rotate_3x2(1));
+}
+
+/// Fills a rectangle of a transparent 4x3 buffer and returns which pixels
+/// took the fill color.
+fn filled_pixels(x: i32, y: i32, w: u32, h: u32) -> Vec<bool> {
+    let mut rgba = vec![0u8; 4 * 3 * 4];
+    unsafe {
+        fill_rect(
+            rgba.as_mut_ptr(),
+            4,
+            3,
+            x,
+            y,
+            w,
+            h,
+            1,
+            2,
+            3,
+            4,
+            std::ptr::null_mut(),
+        )
+    };
+    rgba.chunks_exact(4).map(|px| px == [1, 2, 3, 4]).collect()
+}
+
+#[test]
+fn fill_rect_full_buffer() {
+    assert!(filled_pixels(0, 0, 4, 3).iter().all(|&f| f));
+}
+
+#[test]
+fn fill_rect_interior() {
+    let expected = [0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0].map(|v| v == 1);
+    assert_eq!(filled_pixels(1, 1, 2, 1), expected);
+}
+
+#[test]
+fn fill_rect_partially_clipped() {
+    let expected = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0].map(|v| v == 1);
+    assert_eq!(filled_pixels(-1, 2, 3, 5), expected);
+    assert!(filled_pixels(4, 0, 3, 3).iter().all(|&f| !f));
+}
+
+#[test]
+fn clear_color_repeats_the_pixel() {
+    // 7 pixels is not a power of two, so the last doubling step is partial.
+    for pixels in [1usize, 2, 3, 7, 16, 100] {
+        let mut rgba = vec![0xEEu8; pixels * 4];
+        unsafe { clear_color(rgba.as_mut_ptr(), rgba.len(), 10, 20, 30, 40) };
+        assert_eq!(rgba, [10, 20, 30, 40].repeat(pixels), "{pixels} pixels");
+    }
+}
+
+#[test]
+fn clear_color_partial_pixel_len_is_a_no_op() {
+    let mut rgba = [0xEEu8; 10];
+    unsafe { clear_color(rgba.as_mut_ptr(), 10, 10, 20, 30, 40) };
+    assert_eq!(rgba, [0xEE; 10]);
+}
+
+#[test]
+fn extract_alpha_packs_one_byte_per_pixel() {
+    let rgba = [1, 2, 3, 0, 4, 5, 6, 64, 7, 8, 9, 128, 10, 11, 12, 255u8];
+    let mut a8 = [0xEEu8; 4];
+    unsafe { extract_alpha(rgba.as_ptr(), 4, a8.as_mut_ptr()) };
+    assert_eq!(a8, [0, 64, 128, 255]);
+}
+
+#[test]
+fn premultiply_round_trip_is_within_rounding() {
+    for a in 1..=255u32 {
+        let rgba: Vec<u8> = (0..=255u8)
+            .flat_map(|c| [c, 255 - c, c / 2, a as u8])
+            .collect();
+        let mut round_trip = rgba.clone();
+        unsafe {
+            premultiply_inplace(round_trip.as_mut_ptr(), 256);
+            unpremultiply_inplace(round_trip.as_mut_ptr(), 256);
+        }
+        // Premultiplied bytes step by a / 255, so dividing back out can be
+        // off by half of 255 / a plus the final rounding.
+        let tolerance = 255 / (2 * a) + 1;
+        for (orig, back) in rgba.chunks_exact(4).zip(round_trip.chunks_exact(4)) {
+            assert_eq!(back[3], orig[3]);
+            for c in 0..3 {
+                assert!(
+                    orig[c].abs_diff(back[c]) as u32 <= tolerance,
+                    "a {a}: {orig:?} -> {back:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn unpremultiply_leaves_transparent_pixels_untouched() {
+    let mut rgba = [10u8, 20, 30, 0, 40, 50, 60, 0];
+    unsafe { unpremultiply_inplace(rgba.as_mut_ptr(), 2) };
+    assert_eq!(rgba, [10, 20, 30, 0, 40, 50, 60, 0]);
+}
+
+fn tracked_bbox(tracker: *const DirtyTracker) -> [u32; 4] {
+    let mut bbox = [7u32; 4];
+    unsafe { dirty_tracker_get(tracker, bbox.as_mut_ptr()) };
+    bbox
+}
+
+#[test]
+fn dirty_tracker_starts_empty() {
+    let tracker = dirty_tracker_new();
+    assert_eq!(tracked_bbox(tracker), [u32::MAX, u32::MAX, 0, 0]);
+    unsafe {
+        dirty_tracker_mark(tracker, 3, 3, 0, 5);
+        assert_eq!(tracked_bbox(tracker), [u32::MAX, u32::MAX, 0, 0]);
+        dirty_tracker_free(tracker);
+    }
+}
+
+#[test]
+fn dirty_tracker_unions_overlapping_and_disjoint_rects() {
+    let tracker = dirty_tracker_new();
+    unsafe {
+        dirty_tracker_mark(tracker, 2, 2, 4, 4);
+        dirty_tracker_mark(tracker, 4, 1, 4, 3);
+        assert_eq!(tracked_bbox(tracker), [2, 1, 7, 5]);
+        dirty_tracker_mark(tracker, 20, 30, 1, 1);
+        assert_eq!(tracked_bbox(tracker), [2, 1, 20, 30]);
+        dirty_tracker_free(tracker);
+    }
+}
+
+#[test]
+fn dirty_tracker_records_clipped_region_and_fill() {
+    let tracker = dirty_tracker_new();
+    let mut target = [0u8, 0, 0, 255].repeat(8 * 8);
+    let src = [255u8; 3 * 3 * 4];
+    unsafe {
+        alpha_composite_region_inplace(
+            target.as_mut_ptr(),
+            8,
+            8,
+            32,
+            -1,
+            6,
+            src.as_ptr(),
+            3,
+            3,
+            tracker,
+        );
+        assert_eq!(tracked_bbox(tracker), [0, 6, 1, 7]);
+        fill_rect(target.as_mut_ptr(), 8, 8, 5, 1, 2, 2, 0, 0, 0, 0, tracker);
+        assert_eq!(tracked_bbox(tracker), [0, 1, 6, 7]);
+        dirty_tracker_free(tracker);
+    }
+}
+
+#[test]
+fn flatten_matches_sequential_source_over() {
+    let len = 64 * 4;
+    let layers = pseudo_random_bytes(25, len * 3);
+    let mut expected = layers[..len].to_vec();
+    for layer in layers[len..].chunks_exact(len) {
+        unsafe { source_over_inplace(expected.as_mut_ptr(), layer.as_ptr(), len) };
+    }
+    let mut flat = vec![0u8; len];
+    assert_eq!(
+        unsafe { flatten_layers(layers.as_ptr(), 3, len, flat.as_mut_ptr()) },
+        Status::Ok
+    );
+    assert_eq!(flat, expected);
+}
+
+#[test]
+fn flatten_without_layers_is_rejected_untouched() {
+    let layers = [0u8; 4];
+    let mut dst = [9u8; 4];
+    assert_eq!(
+        unsafe { flatten_layers(layers.as_ptr(), 0, 4, dst.as_mut_ptr()) },
+        Status::InvalidArgument
+    );
+    assert_eq!(dst, [9; 4]);
+}
+
+/// One frame's worth of arena allocations.
+fn arena_frame(sizes: &[usize]) -> Vec<usize> {
+    sizes
+        .iter()
+        .map(|&size| arena_alloc(size) as usize)
+        .collect()
+}
+
+// The arena is process-global, so every arena check lives in this one test.
+#[test]
+fn arena_reuses_addresses_across_resets() {
+    arena_reset();
+    let small = [100, 3, 4096, 17];
+    let first = arena_frame(&small);
+    assert!(first.iter().all(|addr| addr % ARENA_ALIGN == 0));
+    arena_reset();
+    assert_eq!(arena_frame(&small), first);
+
+    // Outgrow the first block: the frame spills into a second one.
+    let large = [100, ARENA_MIN_BLOCK, 3, 4096];
+    let grown = arena_frame(&large);
+    assert!(grown.iter().all(|addr| addr % ARENA_ALIGN == 0));
+    // The first reset merges the blocks into a new one, so only the frame
+    // after that repeats addresses.
+    arena_reset();
+    let merged = arena_frame(&large);
+    arena_reset();
+    assert_eq!(arena_frame(&large), merged);
+    // A smaller frame fits the merged block and starts at its base.
+    arena_reset();
+    assert_eq!(arena_frame(&small)[0], merged[0]);
+
+    // Sizes the arena can never hold fail cleanly and leave it usable.
+    arena_reset();
+    assert!(arena_alloc(usize::MAX).is_null());
+    assert!(arena_alloc(isize::MAX as usize).is_null());
+    assert_eq!(arena_frame(&small)[0], merged[0]);
+}
+
+fn rgba16(channels: &[[u16; 4]]) -> Vec<u8> {
+    channels
+        .iter()
+        .flatten()
+        .flat_map(|c| c.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn u16_composite_matches_float_reference() {
+    let target_alphas = [65535u16, 65535, 40000, 12345, 0, 1000];
+    let occluder_alphas = [0u16, 65535, 32768, 1, 65535, 60000];
+    let target: Vec<[u16; 4]> = target_alphas.iter().map(|&a| [1, 2, 3, a]).collect();
+    let occluder: Vec<[u16; 4]> = occluder_alphas.iter().map(|&a| [9, 9, 9, a]).collect();
+    let mut out = rgba16(&target);
+    let status = unsafe {
+        alpha_composite_inplace_u16(
+            out.as_mut_ptr(),
+            rgba16(&occluder).as_ptr(),
+            out.len(),
+            target.len(),
+        )
+    };
+    assert_eq!(status, Status::Ok);
+
+    for (i, (&ta, &oa)) in target_alphas.iter().zip(&occluder_alphas).enumerate() {
+        let px = &out[i * 8..i * 8 + 8];
+        assert_eq!(px[..6], rgba16(&[[1, 2, 3, 0]])[..6]);
+        let alpha = u16::from_le_bytes([px[6], px[7]]) as f64;
+        let expected = ta as f64 * (1.0 - oa as f64 / 65535.0);
+        assert!(
+            (alpha - expected).abs() < 1.0,
+            "{ta} under {oa}: {alpha} vs {expected}"
+        );
+    }
+}
+
+#[test]
+fn u16_composite_len_mismatch_is_rejected_untouched() {
+    let target = rgba16(&[[1, 2, 3, 65535], [4, 5, 6, 65535]]);
+    let occluder = rgba16(&[[0, 0, 0, 65535], [0, 0, 0, 65535]]);
+    let mut out = target.clone();
+    let status =
+        unsafe { alpha_composite_inplace_u16(out.as_mut_ptr(), occluder.as_ptr(), out.len(), 1) };
+    assert_eq!(status, Status::InvalidArgument);
+    assert_eq!(out, target);
+}
+
+#[test]
+fn alpha_histogram_counts_sum_to_pixel_count() {
+    let rgba: Vec<u8> = [0u8, 10, 10, 255, 128, 255, 10]
+        .iter()
+        .flat_map(|&a| [1, 2, 3, a])
+        .collect();
+    let mut hist = [7u32; 256];
+    unsafe { alpha_histogram(rgba.as_ptr(), 7, hist.as_mut_ptr()) };
+    assert_eq!(hist.iter().sum::<u32>(), 7);
+    assert_eq!((hist[0], hist[10], hist[128], hist[255]), (1, 3, 1, 2));
+}
+
+#[test]
+fn alpha_histogram_of_no_pixels_is_all_zeros() {
+    let mut hist = [7u32; 256];
+    unsafe { alpha_histogram(std::ptr::null(), 0, hist.as_mut_ptr()) };
+    assert_eq!(hist, [0; 256]);
+}
+
+fn min_max(alphas: &[u8]) -> [u8; 2] {
+    let rgba: Vec<u8> = alphas.iter().flat_map(|&a| [9, 9, 9, a]).collect();
+    let mut out = [7u8; 2];
+    unsafe { alpha_min_max(rgba.as_ptr(), alphas.len(), out.as_mut_ptr()) };
+    out
+}
+
+#[test]
+fn alpha_min_max_of_uniform_buffers() {
+    assert_eq!(min_max(&[0; 16]), [0, 0]);
+    assert_eq!(min_max(&[255; 16]), [255, 255]);
+}
+
+#[test]
+fn alpha_min_max_of_mixed_buffers() {
+    assert_eq!(min_max(&[40, 7, 200, 128]), [7, 200]);
+    assert_eq!(min_max(&[255, 3, 0, 9]), [0, 255]);
+    assert_eq!(min_max(&[]), [255, 0]);
+}
+
+type PairOp = unsafe extern "C" fn(*mut u8, *const u8, usize) -> Status;
+
+/// The two-buffer compositing entry points that share one argument shape.
+const PAIR_OPS: [(&str, PairOp); 7] = [
+    ("alpha_composite_inplace", alpha_composite_inplace),
+    ("source_over_inplace", source_over_inplace),
+    ("source_over_premult_inplace", source_over_premult_inplace),
+    ("source_over_linear_inplace", source_over_linear_inplace),
+    ("destination_out_inplace", destination_out_inplace),
+    ("destination_in_inplace", destination_in_inplace),
+    ("add_blend_inplace", add_blend_inplace),
+];
+
+#[test]
+fn status_codes_cross_ffi_as_fixed_integers() {
+    assert_eq!(Status::Ok as i32, 0);
+    assert_eq!(Status::NullPointer as i32, 1);
+    assert_eq!(Status::UnalignedLen as i32, 2);
+    assert_eq!(Status::InvalidArgument as i32, 3);
+}
+
+#[test]
+fn null_pointers_return_null_pointer() {
+    let mut buf = [0u8; 8];
+    for (name, op) in PAIR_OPS {
+        assert_eq!(
+            unsafe { op(std::ptr::null_mut(), buf.as_ptr(), 8) },
+            Status::NullPointer,
+            "{name}"
+        );
+        assert_eq!(
+            unsafe { op(buf.as_mut_ptr(), std::ptr::null(), 8) },
+            Status::NullPointer,
+            "{name}"
+        );
+    }
+    let status = unsafe { blend_inplace(std::ptr::null_mut(), buf.as_ptr(), 8, 0) };
+    assert_eq!(status, Status::NullPointer);
+}
+
+#[test]
+fn partial_pixel_len_returns_unaligned_len_untouched() {
+    let target = pseudo_random_bytes(30, 10);
+    let src = pseudo_random_bytes(31, 10);
+    for (name, op) in PAIR_OPS {
+        let mut out = target.clone();
+        assert_eq!(
+            unsafe { op(out.as_mut_ptr(), src.as_ptr(), 10) },
+            Status::UnalignedLen,
+            "{name}"
+        );
+        assert_eq!(out, target, "{name}");
+    }
+    let mut out = target.clone();
+    assert_eq!(
+        unsafe { blend_inplace(out.as_mut_ptr(), src.as_ptr(), 10, 0) },
+        Status::UnalignedLen
+    );
+    assert_eq!(out, target);
+}
+
+#[test]
+fn bad_mode_returns_invalid_argument_untouched() {
+    let target = pseudo_random_bytes(32, 8);
+    let src = pseudo_random_bytes(33, 8);
+    let mut out = target.clone();
+    assert_eq!(
+        unsafe { blend_inplace(out.as_mut_ptr(), src.as_ptr(), 8, 99) },
+        Status::InvalidArgument
+    );
+    assert_eq!(
+        unsafe { source_over_inplace_fmt(out.as_mut_ptr(), src.as_ptr(), 8, 99) },
+        Status::InvalidArgument
+    );
+    assert_eq!(out, target);
+}
+
+/// `width x height` image with the given alpha inside `[x0, x1) x [y0, y1)`
+/// and `outside` elsewhere.
+fn alpha_rect(
+    width: usize,
+    height: usize,
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    inside: u8,
+    outside: u8,
+) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            let a = if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+                inside
+            } else {
+                outside
+            };
+            [50, 60, 70, a]
+        })
+        .collect()
+}
+
+fn opaque_bounds(rgba: &[u8], width: u32, height: u32) -> [u32; 4] {
+    let mut bbox = [7u32; 4];
+    unsafe { opaque_bbox(rgba.as_ptr(), width, height, bbox.as_mut_ptr()) };
+    bbox
+}
+
+#[test]
+fn opaque_bbox_finds_a_centered_square() {
+    let rgba = alpha_rect(8, 8, (2, 3, 6, 7), 255, 200);
+    assert_eq!(opaque_bounds(&rgba, 8, 8), [2, 3, 5, 6]);
+}
+
+#[test]
+fn opaque_bbox_of_a_fully_opaque_buffer() {
+    assert_eq!(opaque_bounds(&[255u8; 5 * 3 * 4], 5, 3), [0, 0, 4, 2]);
+}
+
+#[test]
+fn opaque_bbox_without_opaque_pixels_is_empty() {
+    let rgba = alpha_rect(4, 4, (0, 0, 4, 4), 254, 0);
+    assert_eq!(opaque_bounds(&rgba, 4, 4), [u32::MAX, u32::MAX, 0, 0]);
+}
+
+#[test]
+fn erode_shrinks_a_square_by_radius() {
+    let mut rgba = alpha_rect(12, 12, (3, 3, 9, 9), 255, 0);
+    unsafe { alpha_erode_inplace(rgba.as_mut_ptr(), 12, 12, 2) };
+    assert_eq!(rgba, alpha_rect(12, 12, (5, 5, 7, 7), 255, 0));
+}
+
+#[test]
+fn dilate_grows_a_square_by_radius() {
+    let mut rgba = alpha_rect(12, 12, (5, 5, 7, 7), 255, 0);
+    unsafe { alpha_dilate_inplace(rgba.as_mut_ptr(), 12, 12, 2) };
+    assert_eq!(rgba, alpha_rect(12, 12, (3, 3, 9, 9), 255, 0));
+}
+
+#[test]
+fn morphology_radius_zero_is_a_no_op() {
+    let rgba = alpha_rect(6, 6, (1, 1, 4, 4), 255, 0);
+    let mut eroded = rgba.clone();
+    let mut dilated = rgba.clone();
+    unsafe {
+        alpha_erode_inplace(eroded.as_mut_ptr(), 6, 6, 0);
+        alpha_dilate_inplace(dilated.as_mut_ptr(), 6, 6, 0);
+    }
+    assert_eq!(eroded, rgba);
+    assert_eq!(dilated, rgba);
+}
+
+#[test]
+fn clip_only_touches_pixels_inside_the_clip() {
+    let (w, h) = (7usize, 5usize);
+    let target = pseudo_random_bytes(33, w * h * 4);
+    let occluder = pseudo_random_bytes(34, w * h * 4);
+    let mut unclipped = target.clone();
+    unsafe { alpha_composite_inplace(unclipped.as_mut_ptr(), occluder.as_ptr(), target.len()) };
+
+    let mut clipped = target.clone();
+    let status = unsafe {
+        alpha_composite_clip_inplace(clipped.as_mut_ptr(), occluder.as_ptr(), 7, 5, -1, 2, 4, 9)
+    };
+    assert_eq!(status, Status::Ok);
+    for i in 0..w * h {
+        let (x, y) = (i % w, i / w);
+        let expected = if x < 3 && y >= 2 { &unclipped } else { &target };
+        assert_eq!(
+            clipped[i * 4..i * 4 + 4],
+            expected[i * 4..i * 4 + 4],
+            "({x}, {y})"
+        );
+    }
+}
+
+#[test]
+fn clip_off_the_image_is_a_no_op() {
+    let target = pseudo_random_bytes(35, 4 * 4 * 4);
+    let mut out = target.clone();
+    let status = unsafe {
+        alpha_composite_clip_inplace(out.as_mut_ptr(), [255u8; 64].as_ptr(), 4, 4, 4, 0, 2, 2)
+    };
+    assert_eq!(status, Status::Ok);
+    assert_eq!(out, target);
+}
+
+fn chroma_key(pixels: &[[u8; 4]], tolerance: u32) -> Vec<u8> {
+    let mut rgba = pixels.concat();
+    unsafe { chroma_key_to_alpha_inplace(rgba.as_mut_ptr(), pixels.len(), 0, 255, 0, tolerance) };
+    alphas(&rgba)
+}
+
+#[test]
+fn chroma_key_exact_match() {
+    let pixels = [[0, 255, 0, 200], [0, 254, 0, 200], [255, 0, 0, 200]];
+    assert_eq!(chroma_key(&pixels, 0), [0, 200, 200]);
+}
+
+#[test]
+fn chroma_key_tolerance_is_squared_distance() {
+    // Squared distances from pure green: 0, 1, 9 + 16 = 25, 26.
+    let pixels = [
+        [0, 255, 0, 9],
+        [1, 255, 0, 9],
+        [3, 251, 0, 9],
+        [3, 251, 1, 9],
+    ];
+    assert_eq!(chroma_key(&pixels, 25), [0, 0, 0, 9]);
+    assert_eq!(chroma_key(&pixels, 1), [0, 0, 9, 9]);
+}
+
+/// 16 pixels of varied color with alpha ramping 0..=255.
+fn alpha_gradient() -> Vec<u8> {
+    (0..16u8)
+        .flat_map(|i| [i * 3, 255 - i, i * 16, i * 17])
+        .collect()
+}
+
+#[test]
+fn tint_makes_a_flat_color_with_identical_alpha() {
+    let src = alpha_gradient();
+    let mut rgba = src.clone();
+    unsafe { tint_inplace(rgba.as_mut_ptr(), 16, 10, 20, 30) };
+    assert_eq!(alphas(&rgba), alphas(&src));
+    assert!(rgba.chunks_exact(4).all(|px| px[..3] == [10, 20, 30]));
+}
+
+#[test]
+fn tint_premult_scales_the_color_by_alpha() {
+    let src = alpha_gradient();
+    let mut rgba = src.clone();
+    unsafe { tint_premult_inplace(rgba.as_mut_ptr(), 16, 255, 128, 0) };
+    assert_eq!(alphas(&rgba), alphas(&src));
+    for px in rgba.chunks_exact(4) {
+        let a = px[3] as u32;
+        assert_eq!(px[..3], [div255(255 * a) as u8, div255(128 * a) as u8, 0]);
+    }
+    assert_eq!(rgba[..4], [0, 0, 0, 0]);
+    assert_eq!(rgba[60..], [255, 128, 0, 255]);
+}
+
+fn grayscale(px: [u8; 4]) -> [u8; 4] {
+    let mut out = px;
+    unsafe { grayscale_inplace(out.as_mut_ptr(), 1) };
+    out
+}
+
+#[test]
+fn grayscale_uses_rec709_weights() {
+    assert_eq!(grayscale([255, 0, 0, 100]), [54, 54, 54, 100]);
+    assert_eq!(grayscale([0, 255, 0, 100]), [182, 182, 182, 100]);
+    assert_eq!(grayscale([0, 0, 255, 100]), [18, 18, 18, 100]);
+}
+
+#[test]
+fn grayscale_keeps_white_and_black() {
+    assert_eq!(grayscale([255, 255, 255, 7]), [255, 255, 255, 7]);
+    assert_eq!(grayscale([0, 0, 0, 7]), [0, 0, 0, 7]);
+}
+
+fn downsample(src: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut dst = vec![0xEEu8; (width / 2 * (height / 2) * 4) as usize];
+    unsafe { downsample_2x(src.as_ptr(), width, height, dst.as_mut_ptr()) };
+    dst
+}
+
+#[test]
+fn downsample_keeps_a_constant_color() {
+    assert_eq!(
+        downsample(&[10, 20, 30, 40].repeat(16), 4, 4),
+        [10, 20, 30, 40].repeat(4)
+    );
+}
+
+#[test]
+fn downsample_averages_a_checkerboard_to_mid_gray() {
+    let src: Vec<u8> = (0..16)
+        .flat_map(|i| {
+            if (i % 4 + i / 4) % 2 == 0 {
+                gray(0)
+            } else {
+                gray(255)
+            }
+        })
+        .collect();
+    assert_eq!(downsample(&src, 4, 4), gray(128).repeat(4));
+}
+
+#[test]
+fn downsample_drops_the_odd_row_and_column() {
+    // 5x3: the last column and row are 255 and must not leak into the result.
+    let src: Vec<u8> = (0..15)
+        .flat_map(|i| {
+            if i % 5 == 4 || i / 5 == 2 {
+                gray(255)
+            } else {
+                gray(0)
+            }
+        })
+        .collect();
+    assert_eq!(downsample(&src, 5, 3), gray(0).repeat(2));
+}
+
+fn gamma_levels(gamma_x1000: u32) -> Vec<u8> {
+    let mut rgba: Vec<u8> = [0u8, 64, 128, 200, 255]
+        .iter()
+        .flat_map(|&v| [v, v, v, 77])
+        .collect();
+    unsafe { adjust_gamma_inplace(rgba.as_mut_ptr(), 5, gamma_x1000) };
+    assert!(alphas(&rgba).iter().all(|&a| a == 77));
+    reds(&rgba)
+}
+
+#[test]
+fn gamma_above_one_darkens() {
+    // round((v / 255)^2 * 255)
+    assert_eq!(gamma_levels(2000), [0, 16, 64, 157, 255]);
+}
+
+#[test]
+fn gamma_below_one_lightens() {
+    // round((v / 255)^0.5 * 255)
+    assert_eq!(gamma_levels(500), [0, 128, 181, 226, 255]);
+}
+
+#[test]
+fn gamma_identity_and_zero_are_no_ops() {
+    assert_eq!(gamma_levels(1000), [0, 64, 128, 200, 255]);
+    assert_eq!(gamma_levels(0), [0, 64, 128, 200, 255]);
+}
+
+#[test]
+fn crate_version_bytes_match_the_package_version() {
+    let bytes = unsafe { std::slice::from_raw_parts(crate_version(), crate_version_len()) };
+    assert_eq!(bytes, env!("CARGO_PKG_VERSION").as_bytes());
+}
+
+#[test]
+fn gaussian_blur_makes_a_symmetric_bell() {
+    let n = 13;
+    let mut rgba = lone_opaque_pixel(n, n, 6, 6);
+    unsafe { gaussian_blur_alpha_inplace(rgba.as_mut_ptr(), n as u32, n as u32, 1000) };
+    let alpha = alphas(&rgba);
+    let at = |x: usize, y: usize| alpha[y * n + x];
+    for y in 0..n {
+        for x in 0..n {
+            assert_eq!(at(x, y), at(n - 1 - x, y), "({x}, {y})");
+            assert_eq!(at(x, y), at(y, x), "({x}, {y})");
+            // The kernel stops at ceil(3 * sigma) = 3 pixels.
+            if x.abs_diff(6) > 3 || y.abs_diff(6) > 3 {
+                assert_eq!(at(x, y), 0, "({x}, {y})");
+            }
+        }
+    }
+    // Falls off monotonically away from the peak.
+    let row: Vec<u8> = (6..10).map(|x| at(x, 6)).collect();
+    assert!(row.windows(2).all(|w| w[0] > w[1]), "{row:?}");
+    assert!(rgba.chunks_exact(4).all(|px| px[..3] == [7, 7, 7]));
+}
+
+#[test]
+fn gaussian_blur_differs_from_box_blur() {
+    let mut gaussian = lone_opaque_pixel(9, 9, 4, 4);
+    let mut boxed = gaussian.clone();
+    unsafe {
+        gaussian_blur_alpha_inplace(gaussian.as_mut_ptr(), 9, 9, 1000);
+        box_blur_alpha_inplace(boxed.as_mut_ptr(), 9, 9, 1);
+    }
+    // The box is flat over its footprint; the bell peaks in the middle.
+    let (g, b) = (alphas(&gaussian), alphas(&boxed));
+    assert_eq!(b[4 * 9 + 4], b[3 * 9 + 3]);
+    assert!(g[4 * 9 + 4] > g[3 * 9 + 3]);
+    assert_ne!(g, b);
+}
+
+#[test]
+fn gaussian_blur_with_a_huge_sigma_spreads_evenly() {
+    // The kernel is capped at the image size, and a sigma far beyond it is
+    // flat across those taps.
+    for sigma_x1000 in [20_000_000, u32::MAX] {
+        let mut rgba = lone_opaque_pixel(5, 5, 2, 2);
+        unsafe { gaussian_blur_alpha_inplace(rgba.as_mut_ptr(), 5, 5, sigma_x1000) };
+        assert_eq!(alphas(&rgba), [10; 25], "sigma_x1000 {sigma_x1000}");
+    }
+}
+
+#[test]
+fn gaussian_blur_sigma_zero_is_a_no_op() {
+    let mut rgba = lone_opaque_pixel(5, 5, 2, 2);
+    let before = rgba.clone();
+    unsafe { gaussian_blur_alpha_inplace(rgba.as_mut_ptr(), 5, 5, 0) };
+    assert_eq!(rgba, before);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn parallel_composite_matches_single_threaded() {
+    // Sizes below, around and well above one chunk per thread.
+    for len in [0, 4, 36, 4000, 1 << 20] {
+        let target = pseudo_random_bytes(41, len);
+        let occluder = pseudo_random_bytes(42, len);
+        let mut scalar = target.clone();
+        let mut parallel = target.clone();
+        unsafe {
+            alpha_composite_inplace(scalar.as_mut_ptr(), occluder.as_ptr(), len);
+            assert_eq!(
+                alpha_composite_inplace_parallel(parallel.as_mut_ptr(), occluder.as_ptr(), len),
+                Status::Ok
+            );
+        }
+        assert_eq!(parallel, scalar, "len {len}");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[test]
+fn parallel_composite_rejects_partial_pixel_len() {
+    let mut target = [7u8; 8];
+    let status =
+        unsafe { alpha_composite_inplace_parallel(target.as_mut_ptr(), [255u8; 8].as_ptr(), 6) };
+    assert_eq!(status, Status::UnalignedLen);
+    assert_eq!(target, [7; 8]);
+}
+
+#[test]
+fn masked_over_with_full_mask_matches_plain_over() {
+    let pixels = 200;
+    let target = pseudo_random_bytes(42, pixels * 4);
+    let src = pseudo_random_bytes(43, pixels * 4);
+    let mut plain = target.clone();
+    let mut masked = target.clone();
+    unsafe {
+        source_over_inplace(plain.as_mut_ptr(), src.as_ptr(), pixels * 4);
+        let status = source_over_masked_inplace(
+            masked.as_mut_ptr(),
+            src.as_ptr(),
+            pixels * 4,
+            [255u8; 200].as_ptr(),
+            pixels,
+        );
+        assert_eq!(status, Status::Ok);
+    }
+    assert_eq!(masked, plain);
+}
+
+#[test]
+fn masked_over_with_half_coverage() {
+    let mut target = [0u8, 0, 255, 255];
+    unsafe {
+        source_over_masked_inplace(
+            target.as_mut_ptr(),
+            [255, 0, 0, 255].as_ptr(),
+            4,
+            [128].as_ptr(),
+            1,
+        )
+    };
+    assert_eq!(target, [128, 0, 127, 255]);
+}
+
+#[test]
+fn masked_over_len_mismatch_is_rejected_untouched() {
+    let target = pseudo_random_bytes(44, 8);
+    let mut out = target.clone();
+    let status = unsafe {
+        source_over_masked_inplace(out.as_mut_ptr(), [0u8; 8].as_ptr(), 8, [255].as_ptr(), 1)
+    };
+    assert_eq!(status, Status::InvalidArgument);
+    assert_eq!(out, target);
+}
+
+const DST_TARGET: [u8; 16] = [10, 20, 30, 200, 1, 2, 3, 255, 4, 5, 6, 100, 7, 8, 9, 0];
+const DST_SRC: [u8; 16] = [99, 99, 99, 128, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 255];
+
+#[test]
+fn destination_out_keeps_target_where_source_is_absent() {
+    let mut target = DST_TARGET;
+    assert_eq!(
+        unsafe { destination_out_inplace(target.as_mut_ptr(), DST_SRC.as_ptr(), 16) },
+        Status::Ok
+    );
+    // ta * (255 - sa) / 255, color untouched.
+    assert_eq!(
+        target,
+        [10, 20, 30, 99, 1, 2, 3, 255, 4, 5, 6, 0, 7, 8, 9, 0]
+    );
+}
+
+#[test]
+fn destination_in_keeps_target_where_source_is_present() {
+    let mut target = DST_TARGET;
+    assert_eq!(
+        unsafe { destination_in_inplace(target.as_mut_ptr(), DST_SRC.as_ptr(), 16) },
+        Status::Ok
+    );
+    // ta * sa / 255, color untouched.
+    assert_eq!(
+        target,
+        [10, 20, 30, 100, 1, 2, 3, 0, 4, 5, 6, 100, 7, 8, 9, 0]
+    );
+}
+
+#[test]
+fn strided_composite_leaves_padding_untouched() {
+    // 6x4 atlas with 8 padding bytes per row; occlude the 2x2 sub-image at
+    // (2, 1) with an occluder whose rows carry 4 padding bytes.
+    let (atlas_w, atlas_h, atlas_stride) = (6, 4, 32);
+    let atlas: Vec<u8> = (0..atlas_h * atlas_stride)
+        .map(|i| {
+            if i % atlas_stride >= atlas_w * 4 {
+                0xEE
+            } else {
+                200
+            }
+        })
+        .collect();
+    let occ_stride = 12;
+    let occluder: Vec<u8> = (0..2 * occ_stride)
+        .map(|i| if i % occ_stride >= 8 { 0x11 } else { 255 })
+        .collect();
+
+    let mut out = atlas.clone();
+    let origin = atlas_stride + 2 * 4;
+    let status = unsafe {
+        alpha_composite_strided_inplace(
+            out[origin..].as_mut_ptr(),
+            occluder.as_ptr(),
+            2,
+            2,
+            atlas_stride as u32,
+            occ_stride as u32,
+        )
+    };
+    assert_eq!(status, Status::Ok);
+    for (i, (&got, &was)) in out.iter().zip(&atlas).enumerate() {
+        let (row, byte) = (i / atlas_stride, i % atlas_stride);
+        let occluded = (1..3).contains(&row) && (8..16).contains(&byte) && byte % 4 == 3;
+        assert_eq!(got, if occluded { 0 } else { was }, "row {row} byte {byte}");
+    }
+}
+
+#[test]
+fn strided_composite_rejects_short_strides() {
+    let target = pseudo_random_bytes(44, 64);
+    let mut out = target.clone();
+    let occluder = [255u8; 64];
+    unsafe {
+        assert_eq!(
+            alpha_composite_strided_inplace(out.as_mut_ptr(), occluder.as_ptr(), 4, 2, 12, 16),
+            Status::InvalidArgument
+        );
+        assert_eq!(
+            alpha_composite_strided_inplace(out.as_mut_ptr(), occluder.as_ptr(), 4, 2, 16, 12),
+            Status::InvalidArgument
+        );
+    }
+    assert_eq!(out, target);
+}
+
+fn rounded_rect(width: u32, height: u32, radius: u32) -> Vec<u8> {
+    let mut mask = vec![7u8; (width * height) as usize];
+    unsafe { generate_rounded_rect_mask(mask.as_mut_ptr(), width, height, radius) };
+    mask
+}
+
+#[test]
+fn rounded_rect_covers_the_center_and_edges() {
+    let mask = rounded_rect(20, 16, 4);
+    let at = |x: usize, y: usize| mask[y * 20 + x];
+    assert_eq!(at(10, 8), 255);
+    // Straight edges away from the corners are fully covered.
+    assert_eq!(
+        (at(10, 0), at(0, 8), at(19, 8), at(10, 15)),
+        (255, 255, 255, 255)
+    );
+}
+
+#[test]
+fn rounded_rect_is_zero_outside_the_arc() {
+    let mask = rounded_rect(20, 16, 8);
+    let at = |x: usize, y: usize| mask[y * 20 + x];
+    for (x, y) in [(0, 0), (19, 0), (0, 15), (19, 15), (1, 1), (0, 1)] {
+        assert_eq!(at(x, y), 0, "({x}, {y})");
+    }
+}
+
+#[test]
+fn rounded_rect_is_partial_on_the_arc() {
+    let mask = rounded_rect(20, 16, 8);
+    let at = |x: usize, y: usize| mask[y * 20 + x];
+    assert!(at(2, 2) > 0 && at(2, 2) < 255, "{}", at(2, 2));
+    // With radius h / 2 the left end is a semicircle, even at mid-height.
+    assert!(at(0, 8) > 240 && at(0, 8) < 255, "{}", at(0, 8));
+    for y in 0..16 {
+        for x in 0..20 {
+            assert_eq!(at(x, y), at(19 - x, y));
+            assert_eq!(at(x, y), at(x, 15 - y));
+        }
+    }
+}
+
+#[test]
+fn rounded_rect_radius_is_clamped_and_zero_is_square() {
+    assert_eq!(rounded_rect(4, 4, 100), rounded_rect(4, 4, 2));
+    assert!(rounded_rect(4, 4, 0).iter().all(|&v| v == 255));
+}
+
+/// 12x12 A8 mask with an opaque square over `[2, 10)` on both axes.
+fn square_mask() -> Vec<u8> {
+    (0..144)
+        .map(|i| {
+            if (2..10).contains(&(i % 12)) && (2..10).contains(&(i / 12)) {
+                255
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn feather_ramps_square_edges_over_edge_px() {
+    let mut mask = square_mask();
+    unsafe { feather_mask_inplace(mask.as_mut_ptr(), 12, 12, 3) };
+    // Three ramp pixels sampled at their centers, then full coverage.
+    assert_eq!(
+        mask[6 * 12..7 * 12],
+        [0, 0, 42, 127, 212, 255, 255, 212, 127, 42, 0, 0]
+    );
+    assert_eq!((mask[2 * 12 + 2], mask[3 * 12 + 3]), (42, 127));
+    for y in 0..12 {
+        for x in 0..12 {
+            assert_eq!(mask[y * 12 + x], mask[x * 12 + y]);
+        }
+    }
+}
+
+#[test]
+fn feather_edge_zero_is_a_no_op() {
+    let mut mask = square_mask();
+    unsafe { feather_mask_inplace(mask.as_mut_ptr(), 12, 12, 0) };
+    assert_eq!(mask, square_mask());
+}
+
+#[test]
+fn feather_scales_partial_alpha_and_ignores_the_image_border() {
+    let mut mask = [128u8, 128, 0];
+    unsafe { feather_mask_inplace(mask.as_mut_ptr(), 3, 1, 2) };
+    assert_eq!(mask, [96, 32, 0]);
+    let mut solid = [255u8; 16];
+    unsafe { feather_mask_inplace(solid.as_mut_ptr(), 4, 4, 2) };
+    assert_eq!(solid, [255; 16]);
+}
+
+fn warp(src: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, matrix: [f32; 6]) -> Vec<u8> {
+    let mut dst = vec![9u8; (dst_w * dst_h * 4) as usize];
+    unsafe {
+        warp_affine(
+            src.as_ptr(),
+            src_w,
+            src_h,
+            dst.as_mut_ptr(),
+            dst_w,
+            dst_h,
+            matrix.as_ptr(),
+        )
+    };
+    dst
+}
+
+const IDENTITY: [f32; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+#[test]
+fn warp_identity_is_a_copy() {
+    let src = numbered(3, 2);
+    assert_eq!(warp(&src, 3, 2, 3, 2, IDENTITY), src);
+}
+
+#[test]
+fn warp_2x_scale_matches_bilinear_resize_inside() {
+    let src: Vec<u8> = (0..16).flat_map(|i| gray((i * 16) as u8)).collect();
+    let up = warp(&src, 4, 4, 8, 8, [0.5, 0.0, 0.0, 0.5, 0.0, 0.0]);
+    let mut resized = vec![0u8; 8 * 8 * 4];
+    unsafe { resize_bilinear(src.as_ptr(), 4, 4, resized.as_mut_ptr(), 8, 8) };
+    for y in 1..7 {
+        for x in 1..7 {
+            let i = (y * 8 + x) * 4;
+            assert_eq!(up[i..i + 4], resized[i..i + 4], "({x}, {y})");
+        }
+    }
+    // The border blends with transparency instead of clamping.
+    assert_eq!(up[3], 143);
+}
+
+#[test]
+fn warp_quarter_turn_matches_rotate_quarter() {
+    let src = numbered(3, 2);
+    let mut rotated = vec![0u8; src.len()];
+    unsafe { rotate_quarter(src.as_ptr(), 3, 2, 1, rotated.as_mut_ptr()) };
+    // Clockwise turn of a 3x2 image: destination (x, y) reads source (y, 2 - x).
+    assert_eq!(
+        warp(&src, 3, 2, 2, 3, [0.0, -1.0, 1.0, 0.0, 0.0, 2.0]),
+        rotated
+    );
+}
+
+#[test]
+fn warp_outside_the_source_is_transparent() {
+    let src = numbered(3, 2);
+    assert!(warp(&src, 3, 2, 3, 2, [1.0, 0.0, 0.0, 1.0, 10.0, 0.0])
+        .iter()
+        .all(|&b| b == 0));
+    assert!(warp(&src, 3, 2, 3, 2, [f32::NAN, 0.0, 0.0, 1.0, 0.0, 0.0])
+        .iter()
+        .all(|&b| b == 0));
+}
+
+fn gray_ramp() -> Vec<u8> {
+    (0..=255u8).flat_map(|v| [v, v, v, 7]).collect()
+}
+
+#[test]
+fn luminance_to_alpha_turns_a_gray_ramp_into_an_alpha_ramp() {
+    let mut rgba = gray_ramp();
+    unsafe { luminance_to_alpha_inplace(rgba.as_mut_ptr(), 256, 0) };
+    for (v, px) in rgba.chunks_exact(4).enumerate() {
+        assert_eq!(px, [v as u8; 4]);
+    }
+}
+
+#[test]
+fn luminance_to_alpha_inverted() {
+    let mut rgba = gray_ramp();
+    unsafe { luminance_to_alpha_inplace(rgba.as_mut_ptr(), 256, 1) };
+    for (v, px) in rgba.chunks_exact(4).enumerate() {
+        assert_eq!(px, [v as u8, v as u8, v as u8, 255 - v as u8]);
+    }
+}
+
+fn add_blend(target: [u8; 4], src: [u8; 4]) -> [u8; 4] {
+    let mut out = target;
+    assert_eq!(
+        unsafe { add_blend_inplace(out.as_mut_ptr(), src.as_ptr(), 4) },
+        Status::Ok
+    );
+    out
+}
+
+#[test]
+fn add_blend_sums_toward_white() {
+    assert_eq!(
+        add_blend([100, 100, 100, 255], [100, 100, 100, 255]),
+        [200, 200, 200, 255]
+    );
+    assert_eq!(
+        add_blend([128, 128, 128, 255], [128, 128, 128, 255]),
+        [255, 255, 255, 255]
+    );
+    // Source color is weighted by its alpha; output alpha is the larger one.
+    assert_eq!(
+        add_blend([1, 2, 3, 4], [255, 255, 255, 128]),
+        [129, 130, 131, 128]
+    );
+}
+
+#[test]
+fn add_blend_transparent_source_is_a_no_op() {
+    assert_eq!(
+        add_blend([10, 20, 30, 40], [99, 99, 99, 0]),
+        [10, 20, 30, 40]
+    );
+}
+
+#[test]
+fn stats_count_full_partial_and_untouched_pixels() {
+    let target = [200u8; 16];
+    let occluder = [0, 0, 0, 255, 0, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 1u8];
+    let mut plain = target;
+    unsafe { alpha_composite_inplace(plain.as_mut_ptr(), occluder.as_ptr(), 16) };
+
+    let mut out = target;
+    // One slot past the stats, to catch overruns.
+    let mut stats = [7u32; 4];
+    let status = unsafe {
+        alpha_composite_stats_inplace(out.as_mut_ptr(), occluder.as_ptr(), 16, stats.as_mut_ptr())
+    };
+    assert_eq!(status, Status::Ok);
+    assert_eq!(stats, [1, 2, 1, 7]);
+    assert_eq!(out, plain);
+}
+
+#[test]
+fn trim_copies_the_tight_bounds_of_a_centered_blob() {
+    // 5x4 with a blob over (1, 1)..=(3, 2); the blob's edge pixels are faint.
+    let src: Vec<u8> = (0..20u8)
+        .flat_map(|i| {
+            let (x, y) = (i % 5, i / 5);
+            let a = match (x, y) {
+                (2, 1..=2) => 255,
+                (1..=3, 1..=2) => 10,
+                _ => 0,
+            };
+            [i, 50, 60, a]
+        })
+        .collect();
+    let mut bbox = [9u32; 4];
+    unsafe { alpha_bbox(src.as_ptr(), 5, 4, bbox.as_mut_ptr()) };
+    assert_eq!(bbox, [1, 1, 3, 2]);
+
+    // One spare pixel at the end catches overruns.
+    let mut dst = vec![0xAAu8; 3 * 2 * 4 + 4];
+    let mut trimmed = [9u32; 4];
+    unsafe { trim_transparent(src.as_ptr(), 5, 4, dst.as_mut_ptr(), trimmed.as_mut_ptr()) };
+    assert_eq!(trimmed, bbox);
+    assert_eq!(reds(&dst), [6, 7, 8, 11, 12, 13, 0xAA]);
+}
+
+#[test]
+fn trim_of_a_transparent_image_is_empty_and_copies_nothing() {
+    let src = [5u8, 5, 5, 0].repeat(6);
+    let mut dst = [0xAAu8; 4];
+    let mut bbox = [9u32; 4];
+    unsafe { trim_transparent(src.as_ptr(), 3, 2, dst.as_mut_ptr(), bbox.as_mut_ptr()) };
+    assert_eq!(bbox, [u32::MAX, u32::MAX, 0, 0]);
+    assert_eq!(dst, [0xAA; 4]);
+}
+
+#[test]
+fn premult_downsample_ignores_transparent_color() {
+    // One opaque gray pixel and three transparent-but-red ones.
+    let src = [
+        [128, 128, 128, 255],
+        [255, 0, 0, 0],
+        [255, 0, 0, 0],
+        [255, 0, 0, 0],
+    ]
+    .concat();
+    let mut straight = [0u8; 4];
+    let mut premult = [0u8; 4];
+    unsafe {
+        downsample_2x(src.as_ptr(), 2, 2, straight.as_mut_ptr());
+        downsample_2x_premult(src.as_ptr(), 2, 2, premult.as_mut_ptr());
+    }
+    // The straight average bleeds the hidden red in; the premultiplied one
+    // stays neutral.
+    assert_eq!(straight, [223, 32, 32, 64]);
+    assert_eq!(premult, [128, 128, 128, 64]);
+}
+
+#[test]
+fn premult_downsample_weights_color_by_coverage() {
+    let src = [
+        [255, 0, 0, 255],
+        [0, 0, 255, 85],
+        [0, 0, 0, 0],
+        [0, 0, 0, 0],
+    ]
+    .concat();
+    let mut dst = [0u8; 4];
+    unsafe { downsample_2x_premult(src.as_ptr(), 2, 2, dst.as_mut_ptr()) };
+    assert_eq!(dst, [191, 0, 64, 85]);
+}
+
+#[test]
+fn premult_downsample_of_transparent_or_opaque_blocks() {
+    let mut dst = [9u8; 4];
+    unsafe {
+        downsample_2x_premult(
+            [200u8, 10, 10, 0].repeat(4).as_ptr(),
+            2,
+            2,
+            dst.as_mut_ptr(),
+        )
+    };
+    assert_eq!(dst, [0; 4]);
+
+    let opaque: Vec<u8> = (0..36u8)
+        .flat_map(|i| [i.wrapping_mul(7), i * 3, 200, 255])
+        .collect();
+    let mut straight = vec![0u8; 36];
+    let mut premult = vec![0u8; 36];
+    unsafe {
+        downsample_2x(opaque.as_ptr(), 6, 6, straight.as_mut_ptr());
+        downsample_2x_premult(opaque.as_ptr(), 6, 6, premult.as_mut_ptr());
+    }
+    assert_eq!(premult, straight);
+}
+
+fn brightness_contrast(brightness: i32, contrast_x1000: u32) -> Vec<u8> {
+    let mut rgba: Vec<u8> = [0u8, 64, 128, 200, 255]
+        .iter()
+        .flat_map(|&v| [v, v, v, 77])
+        .collect();
+    unsafe { adjust_brightness_contrast_inplace(rgba.as_mut_ptr(), 5, brightness, contrast_x1000) };
+    assert!(alphas(&rgba).iter().all(|&a| a == 77));
+    reds(&rgba)
+}
+
+#[test]
+fn brightness_contrast_identity_is_a_no_op() {
+    let mut rgba = pseudo_random_bytes(53, 256);
+    let before = rgba.clone();
+    unsafe { adjust_brightness_contrast_inplace(rgba.as_mut_ptr(), 64, 0, 1000) };
+    assert_eq!(rgba, before);
+    assert_eq!(brightness_contrast(0, 1000), [0, 64, 128, 200, 255]);
+}
+
+#[test]
+fn brightness_shifts_and_clamps() {
+    assert_eq!(brightness_contrast(20, 1000), [20, 84, 148, 220, 255]);
+    assert_eq!(brightness_contrast(-300, 1000), [0; 5]);
+}
+
+#[test]
+fn contrast_scales_about_mid_gray() {
+    assert_eq!(brightness_contrast(0, 2000), [0, 0, 128, 255, 255]);
+    assert_eq!(brightness_contrast(0, 500), [64, 96, 128, 164, 192]);
+    assert_eq!(brightness_contrast(10, 0), [138; 5]);
+    assert_eq!(brightness_contrast(-10, 1500), [0, 22, 118, 226, 255]);
+}
@@ -28,7 +28,15 @@ type JobResult =
 type WasmExports = {
   memory: WebAssembly.Memory;
   alloc: (size: number) => number;
-  alpha_composite_inplace: (ptrTarget: number, ptrOcc: number, len: number) => void;
+  dealloc?: (ptr: number, size: number) => void;
+  alpha_composite_inplace: (ptrTarget: number, ptrOcc: number, len: number) => number;
+};
+
+// Mirrors the `Status` enum returned by the wasm-occlusion compositing exports.
+const WASM_STATUS_NAMES: Record<number, string> = {
+  1: 'NullPointer',
+  2: 'UnalignedLen',
+  3: 'InvalidArgument',
 };
 
 let wasmExports: WasmExports | null = null;
@@ -43,12 +51,14 @@ const ensureWasmBuffer = (kind: 'target' | 'occ', size: number): number => {
 
   if (kind === 'target') {
     if (wasmTargetCap >= size && wasmTargetPtr !== 0) return wasmTargetPtr;
+    if (wasmTargetPtr !== 0) wasmExports.dealloc?.(wasmTargetPtr, wasmTargetCap);
     wasmTargetPtr = wasmExports.alloc(size);
     wasmTargetCap = size;
     return wasmTargetPtr;
   }
 
   if (wasmOccCap >= size && wasmOccPtr !== 0) return wasmOccPtr;
+  if (wasmOccPtr !== 0) wasmExports.dealloc?.(wasmOccPtr, wasmOccCap);
   wasmOccPtr = wasmExports.alloc(size);
   wasmOccCap = size;
   return wasmOccPtr;
@@ -100,11 +110,12 @@ async function decodePngToRgba(png: Uint8Array): Promise<{ data: Uint8ClampedArr
         const exportsObj = instance.exports as any;
         const memory = exportsObj.memory as WebAssembly.Memory | undefined;
         const alloc = exportsObj.alloc as ((size: number) => number) | undefined;
-        const alpha = exportsObj.alpha_composite_inplace as ((a: number, b: number, c: number) => void) | undefined;
+        const dealloc = exportsObj.dealloc as ((ptr: number, size: number) => void) | undefined;
+        const alpha = exportsObj.alpha_composite_inplace as ((a: number, b: number, c: number) => number) | undefined;
         if (!memory || !alloc || !alpha) {
           throw new Error('WASM exports missing: memory/alloc/alpha_composite_inplace');
         }
-        wasmExports = { memory, alloc, alpha_composite_inplace: alpha };
+        wasmExports = { memory, alloc, dealloc, alpha_composite_inplace: alpha };
         wasmTargetPtr = 0;
         wasmTargetCap = 0;
         wasmOccPtr = 0;
@@ -146,7 +157,10 @@ async function decodePngToRgba(png: Uint8Array): Promise<{ data: Uint8ClampedArr
               const mem = new Uint8Array(wasmExports.memory.buffer);
               mem.set(out, tPtr);
               mem.set(occ.data, oPtr);
-              wasmExports.alpha_composite_inplace(tPtr, oPtr, total);
+              const status = wasmExports.alpha_composite_inplace(tPtr, oPtr, total);
+              if (status !== 0) {
+                throw new Error(`alpha_composite_inplace failed: ${WASM_STATUS_NAMES[status] ?? `status ${status}`}`);
+              }
               out.set(mem.subarray(tPtr, tPtr + total));
             } else {
               for (let i = 0; i < total; i += 4) {